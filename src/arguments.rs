@@ -1,6 +1,12 @@
 use std::env::ArgsOs;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::output::Precompress;
 
 macro_rules! mark_used {
 	($used:tt) => {};
@@ -13,6 +19,30 @@ macro_rules! arg_parse_error {
 	}};
 }
 
+pub fn parse_epoch(epoch: &str) -> Option<DateTime<Utc>> {
+	let seconds = epoch.trim().parse().ok()?;
+	Utc.timestamp_opt(seconds, 0).single()
+}
+
+//Either a fixed offset such as `+02:00` or a named zone which follows daylight saving
+#[derive(Debug, Clone, Copy)]
+pub enum Timezone {
+	Fixed(FixedOffset),
+	Named(Tz),
+}
+
+fn parse_timezone(timezone: &str) -> Option<Timezone> {
+	if timezone == "Z" {
+		return Some(Timezone::Fixed(FixedOffset::east_opt(0)?));
+	}
+
+	if let Ok(offset) = timezone.parse::<FixedOffset>() {
+		return Some(Timezone::Fixed(offset));
+	}
+
+	timezone.parse::<Tz>().ok().map(Timezone::Named)
+}
+
 fn get_next_arg(args: &mut ArgsOs) -> OsString {
 	if let Some(arg) = args.next() {
 		arg
@@ -21,10 +51,107 @@ fn get_next_arg(args: &mut ArgsOs) -> OsString {
 	}
 }
 
+//Splits `--name=value` into the flag and its value, anything else is left whole
+fn split_inline_value(selector: OsString) -> (OsString, Option<OsString>) {
+	if let Some(selector_str) = selector.to_str() {
+		if selector_str.starts_with('-') {
+			if let Some((flag, value)) = selector_str.split_once('=') {
+				return (flag.into(), Some(value.into()));
+			}
+		}
+	}
+
+	(selector, None)
+}
+
+fn get_value_arg(args: &mut ArgsOs, inline_value: &mut Option<OsString>) -> OsString {
+	match inline_value.take() {
+		Some(value) => value,
+		None => get_next_arg(args),
+	}
+}
+
+fn reject_inline_value(selector: &OsString, inline_value: &Option<OsString>) {
+	if let Some(value) = inline_value {
+		arg_parse_error!(
+			"Flag '{}' does not take a value, got '{}'",
+			selector.to_string_lossy(),
+			value.to_string_lossy()
+		);
+	}
+}
+
+fn unexpected_argument(selector: &OsString) -> ! {
+	let selector = selector.to_string_lossy();
+
+	//Some short flags are several letters long so bundles like `-hV` cannot be split apart
+	if selector.starts_with('-') && !selector.starts_with("--") && selector.len() > 2 {
+		arg_parse_error!(
+			"Unexpected argument '{}', short flags cannot be combined and must be passed separately",
+			selector
+		);
+	}
+
+	arg_parse_error!("Unexpected argument '{}'", selector);
+}
+
+//Picked up from the working directory when no config file is given
+const DEFAULT_CONFIG_FILE: &str = "floc.toml";
+
+fn load_config(path: &Path) -> toml::Table {
+	let contents = match std::fs::read_to_string(path) {
+		Ok(contents) => contents,
+		Err(err) => arg_parse_error!(
+			"Failed to read config file '{}': {}",
+			path.to_string_lossy(),
+			err
+		),
+	};
+
+	match contents.parse() {
+		Ok(table) => table,
+		Err(err) => arg_parse_error!(
+			"Failed to parse config file '{}': {}",
+			path.to_string_lossy(),
+			err
+		),
+	}
+}
+
+//A flag without an argument is switched on in a config file with `key = true`
+fn config_flag(key: &str, value: toml::Value) -> bool {
+	match value {
+		toml::Value::Boolean(enabled) => enabled,
+		_ => arg_parse_error!("Config key '{}' must be true or false", key),
+	}
+}
+
+fn config_value(key: &str, value: toml::Value) -> OsString {
+	match value {
+		toml::Value::String(value) => value.into(),
+		toml::Value::Integer(value) => value.to_string().into(),
+		toml::Value::Float(value) => value.to_string().into(),
+		toml::Value::Datetime(value) => value.to_string().into(),
+		_ => arg_parse_error!("Config key '{}' must be a single value", key),
+	}
+}
+
+fn config_list(key: &str, value: toml::Value) -> Vec<OsString> {
+	match value {
+		toml::Value::Array(values) => values
+			.into_iter()
+			.map(|value| config_value(key, value))
+			.collect(),
+		value => vec![config_value(key, value)],
+	}
+}
+
 macro_rules! define_flags {
 	(
 		$app_name:literal, $app_description:literal
 
+		config $config_name:ident ($config_short_flag:literal, $config_long_flag:literal) $config_blurb:literal,
+
 		$(
 			activity $activity_name:ident ($activity_short_flag:literal, $activity_long_flag:literal) $activity_blurb:literal
 			$({ without_arg() $activity_without_arg_block:block })?
@@ -37,6 +164,11 @@ macro_rules! define_flags {
 			$({ with_arg($optional_with_arg_name:ident) $optional_with_arg_block:block} )?
 		,)*
 
+		$(
+			repeated $repeated_name:ident ($repeated_short_flag:literal, $repeated_long_flag:literal) $repeated_blurb:literal -> $repeated_return_type:ty, min $repeated_min:literal
+			$({ with_arg($repeated_with_arg_name:ident) $repeated_with_arg_block:block })?
+		,)*
+
 		$(
 			required $required_name:ident ($required_short_flag:literal, $required_long_flag:literal) $required_blurb:literal -> $required_return_type:ty
 			$({ without_arg() $required_without_arg_block:block })?
@@ -46,9 +178,22 @@ macro_rules! define_flags {
 		#[derive(Debug, Clone)]
 		pub struct Arguments {
 			$(pub $optional_name: Option<$optional_return_type> ,)*
+			$(pub $repeated_name: Vec<$repeated_return_type> ,)*
 			$(pub $required_name: $required_return_type ,)*
 		}
 
+		impl Arguments {
+			//Every other flag left unset, for unit tests which only care about a few
+			#[cfg(test)]
+			pub fn with_required($($required_name: $required_return_type),*) -> Arguments {
+				Arguments {
+					$($optional_name: None,)*
+					$($repeated_name: Vec::new(),)*
+					$($required_name,)*
+				}
+			}
+		}
+
 		struct FlagParser;
 
 		impl FlagParser {
@@ -58,33 +203,49 @@ macro_rules! define_flags {
 			$($( fn $optional_name() -> $optional_return_type $optional_without_arg_block )?)?
 			$($( fn $optional_name($optional_with_arg_name: OsString) -> $optional_return_type $optional_with_arg_block )?)?
 
+			$($( fn $repeated_name($repeated_with_arg_name: OsString) -> $repeated_return_type $repeated_with_arg_block )?)?
+
 			$($( fn $required_name() -> $required_return_type $required_without_arg_block )?)?
 			$($( fn $required_name($required_with_arg_name: OsString) -> $required_return_type $required_with_arg_block )?)?
 		}
 
 		pub fn parse() -> Arguments {
 			struct ValueTracker {
+				$config_name: Option<PathBuf>,
 				$($optional_name: Option<$optional_return_type> ,)*
+				$($repeated_name: Vec<$repeated_return_type> ,)*
 				$($required_name: Option<$required_return_type> ,)*
 			}
 
 			let mut tracker = ValueTracker {
+				$config_name: None,
 				$($optional_name: None ,)*
+				$($repeated_name: Vec::new() ,)*
 				$($required_name: None ,)*
 			};
 
 			let mut args = std::env::args_os();
 			args.next().expect("There was no first argument to dispose of");
 			while let Some(selector) = args.next() {
+				let (selector, mut inline_value) = split_inline_value(selector);
 				match selector.to_str() {
+					Some($config_short_flag) | Some($config_long_flag) => {
+						if tracker.$config_name.is_some() {
+							arg_parse_error!("Flag '{}' specified twice", $config_long_flag);
+						}
+
+						tracker.$config_name = Some(get_value_arg(&mut args, &mut inline_value).into());
+					}
+
 					$(Some($activity_short_flag) | Some($activity_long_flag) => {
 						(|| {
 							$(
+								reject_inline_value(&selector, &inline_value);
 								return FlagParser::$activity_name();
 								mark_used!($activity_without_arg_block);
 							)?
 							$(
-								let next = get_next_arg(&mut args);
+								let next = get_value_arg(&mut args, &mut inline_value);
 								return FlagParser::$activity_name(next);
 								mark_used!($activity_with_arg_block);
 							)?
@@ -92,40 +253,133 @@ macro_rules! define_flags {
 					})*
 
 					$(Some($optional_short_flag) | Some($optional_long_flag) => {
+						if tracker.$optional_name.is_some() {
+							arg_parse_error!("Flag '{}' specified twice", $optional_long_flag);
+						}
+
 						tracker.$optional_name = Some((|| {
 							$(
+								reject_inline_value(&selector, &inline_value);
 								return FlagParser::$optional_name();
 								mark_used!($optional_without_arg_block);
 							)?
 							$(
-								let next = get_next_arg(&mut args);
+								let next = get_value_arg(&mut args, &mut inline_value);
 								return FlagParser::$optional_name(next);
 								mark_used!($optional_with_arg_block);
 							)?
 						})());
 					})*
 
+					$(Some($repeated_short_flag) | Some($repeated_long_flag) => {
+						tracker.$repeated_name.push((|| {
+							$(
+								let next = get_value_arg(&mut args, &mut inline_value);
+								return FlagParser::$repeated_name(next);
+								mark_used!($repeated_with_arg_block);
+							)?
+						})());
+					})*
+
 					$(Some($required_short_flag) | Some($required_long_flag) => {
+						if tracker.$required_name.is_some() {
+							arg_parse_error!("Flag '{}' specified twice", $required_long_flag);
+						}
+
 						tracker.$required_name = Some((|| {
 							$(
+								reject_inline_value(&selector, &inline_value);
 								return FlagParser::$required_name();
 								mark_used!($required_without_arg_block);
 							)?
 							$(
-								let next = get_next_arg(&mut args);
+								let next = get_value_arg(&mut args, &mut inline_value);
 								return FlagParser::$required_name(next);
 								mark_used!($required_with_arg_block);
 							)?
 						})());
 					})*
 
-					_ => arg_parse_error!("Unexpected argument '{}'", selector.to_string_lossy()),
+					_ => unexpected_argument(&selector),
+				}
+			}
+
+			let mut detected_config = false;
+			if tracker.$config_name.is_none() && Path::new(DEFAULT_CONFIG_FILE).is_file() {
+				tracker.$config_name = Some(PathBuf::from(DEFAULT_CONFIG_FILE));
+				detected_config = true;
+			}
+
+			//Values from the config file only fill in what the command line left unset
+			if let Some(config_path) = &tracker.$config_name {
+				for (key, value) in load_config(config_path) {
+					let selector = format!("--{}", key);
+					match selector.as_str() {
+						$($optional_long_flag => {
+							if tracker.$optional_name.is_none() {
+								tracker.$optional_name = (|| {
+									$(
+										if config_flag(&key, value) {
+											return Some(FlagParser::$optional_name());
+										}
+										return None;
+										mark_used!($optional_without_arg_block);
+									)?
+									$(
+										return Some(FlagParser::$optional_name(config_value(&key, value)));
+										mark_used!($optional_with_arg_block);
+									)?
+								})();
+							}
+						})*
+
+						$($repeated_long_flag => {
+							if tracker.$repeated_name.is_empty() {
+								for value in config_list(&key, value) {
+									tracker.$repeated_name.push((|| {
+										$(
+											return FlagParser::$repeated_name(value);
+											mark_used!($repeated_with_arg_block);
+										)?
+									})());
+								}
+							}
+						})*
+
+						$($required_long_flag => {
+							if tracker.$required_name.is_none() {
+								tracker.$required_name = (|| {
+									$(
+										if config_flag(&key, value) {
+											return Some(FlagParser::$required_name());
+										}
+										return None;
+										mark_used!($required_without_arg_block);
+									)?
+									$(
+										return Some(FlagParser::$required_name(config_value(&key, value)));
+										mark_used!($required_with_arg_block);
+									)?
+								})();
+							}
+						})*
+
+						_ => arg_parse_error!("Unknown key '{}' in config file '{}'", key, config_path.to_string_lossy()),
+					}
 				}
 			}
 
 			$(
 				let $optional_name = tracker.$optional_name;
 			)*
+			$(
+				let $repeated_name = tracker.$repeated_name;
+				#[allow(unused_comparisons)] //A minimum of zero can never be violated
+				let too_few = $repeated_name.len() < $repeated_min;
+				if too_few {
+					arg_parse_error!("Flag '{}' must be given at least {} time(s)", $repeated_long_flag, $repeated_min);
+				}
+			)*
 			$(
 				let $required_name = if let Some(value) = tracker.$required_name {
 					value
@@ -134,17 +388,24 @@ macro_rules! define_flags {
 				};
 			)*
 
-			Arguments {
+			let arguments = Arguments {
 				$($optional_name,)*
+				$($repeated_name,)*
 				$($required_name,)*
+			};
+
+			if detected_config {
+				info!(arguments, "Using config file '{}'", DEFAULT_CONFIG_FILE);
 			}
+
+			arguments
 		}
 
 		pub fn print_help() {
 			const INDENT: &str = "    ";
 
 			let max_width_spaces = {
-				let mut max_width = 0;
+				let mut max_width = stringify!($config_short_flag).len() + stringify!($config_long_flag).len();
 
 				$({
 					let mut width = 0;
@@ -158,6 +419,12 @@ macro_rules! define_flags {
 					width += stringify!($optional_long_flag).len();
 					max_width = max_width.max(width);
 				})*
+				$({
+					let mut width = 0;
+					width += stringify!($repeated_short_flag).len();
+					width += stringify!($repeated_long_flag).len();
+					max_width = max_width.max(width);
+				})*
 				$({
 					let mut width = 0;
 					width += stringify!($required_short_flag).len();
@@ -190,12 +457,25 @@ macro_rules! define_flags {
 			println!();
 
 			println!("FLAGS:");
+			{
+				print!("{}", INDENT);
+				print!("{} {}", stringify!($config_short_flag), stringify!($config_long_flag));
+				let len = stringify!($config_short_flag).len() + stringify!($config_long_flag).len() + 4 + 2;
+				println!("{}{}(optional) {}", &max_width_spaces[len..], INDENT, $config_blurb);
+			}
 			$(
 				print!("{}", INDENT);
 				print!("{} {}", stringify!($optional_short_flag), stringify!($optional_long_flag));
 				let len = stringify!($optional_short_flag).len() + stringify!($optional_long_flag).len() + 4 + 2;
 				println!("{}{}(optional) {}", &max_width_spaces[len..], INDENT, $optional_blurb);
 			)*
+			$(
+				print!("{}", INDENT);
+				print!("{} {}", stringify!($repeated_short_flag), stringify!($repeated_long_flag));
+				let len = stringify!($repeated_short_flag).len() + stringify!($repeated_long_flag).len() + 4 + 2;
+				let kind = if $repeated_min > 0 { "required, repeatable" } else { "repeatable" };
+				println!("{}{}({}) {}", &max_width_spaces[len..], INDENT, kind, $repeated_blurb);
+			)*
 			$(
 				print!("{}", INDENT);
 				print!("{} {}", stringify!($required_short_flag), stringify!($required_long_flag));
@@ -211,6 +491,8 @@ macro_rules! define_flags {
 define_flags! {
 	"floc_blog", "floc_blog, a small bare bones static blog generator"
 
+	config config ("-cf", "--config") "TOML file of flag values keyed by long flag name, defaults to './floc.toml' if present, command line flags take precedence",
+
 	activity print_help ("-h", "--help") "Print this help message" {
 		without_arg() {
 			print_help();
@@ -218,12 +500,25 @@ define_flags! {
 		}
 	},
 
-	optional favicon ("-s", "--favicon") "Favicon image for generated pages" -> String {
+	activity print_version ("-V", "--version") "Print the version and exit" {
+		without_arg() {
+			println!("floc_blog {}", crate::VERSION);
+			std::process::exit(0);
+		}
+	},
+
+	optional favicon ("-s", "--favicon") "Favicon image linked from generated pages" -> String {
 		with_arg(favicon) {
 			favicon.to_string_lossy().into()
 		}
 	},
 
+	optional og_default_image ("-oi", "--og-default-image") "Social preview image for posts which do not set their own" -> String {
+		with_arg(image) {
+			image.to_string_lossy().into()
+		}
+	},
+
 	optional language ("-l", "--language") "Language to specify in generated output" -> String {
 		with_arg(language) {
 			language.to_string_lossy().into()
@@ -242,25 +537,403 @@ define_flags! {
 		}
 	},
 
+	optional atom ("-a", "--atom") "Also generate Atom feeds alongside the RSS feeds" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional full_content_feed ("-c", "--full-content-feed") "Include the full rendered post in feed items, not just the description" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional json_feed ("-j", "--json-feed") "Also generate JSON Feed 1.1 feeds alongside the RSS feeds" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional structured_data ("-sd", "--structured-data") "Embed JSON-LD BlogPosting structured data in each post page" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional no_canonical ("-nc", "--no-canonical") "Do not emit a canonical link in each post page" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional smart_punctuation ("-sp", "--smart-punctuation") "Render straight quotes, dashes and ellipses as their typographic forms" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional auto_description ("-ad", "--auto-description") "Derive a missing post description from the first paragraph instead of erroring" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional default_author ("-da", "--default-author") "Author to credit on posts which do not name one" -> String {
+		with_arg(author) {
+			author.to_string_lossy().into()
+		}
+	},
+
+	optional include_drafts ("-d", "--include-drafts") "Build posts marked as drafts too, for previewing" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional warn_future ("-wf", "--warn-future") "Print a warning for each post dated in the future" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional skip_future ("-sf", "--skip-future") "Leave posts dated in the future out of the output entirely" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional clean ("-cl", "--clean") "DANGER: Deletes the ENTIRE output directory and EVERYTHING in it before building, double check -o" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional incremental ("-ic", "--incremental") "Reuse the previous build's render of posts whose markdown, fragments and flags are unchanged" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional cache_path ("-cp", "--cache-path") "Where --incremental keeps its manifest of rendered posts, .floc-cache.json in the current directory by default" -> PathBuf {
+		with_arg(path) {
+			path.into()
+		}
+	},
+
+	optional dry_run ("-n", "--dry-run") "Run the whole build but only print the files it would write instead of writing them" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional verbose ("-v", "--verbose") "Log every file read and written during the build" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional quiet ("-q", "--quiet") "Print nothing but fatal errors, takes precedence over --verbose" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional watch ("-w", "--watch") "Keep running and rebuild whenever the input or fragments change" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional serve ("-sv", "--serve") "Serve the output directory over HTTP after building, pairs with --watch for previews" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional search_index ("-si", "--search-index") "Also generate a search-index.json holding the plain text of every listed post" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional sitemap ("-m", "--sitemap") "Also generate a sitemap.xml listing every post" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional robots ("-rb", "--robots") "Also generate a robots.txt allowing all crawlers and pointing at the sitemap if there is one" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional archive ("-ar", "--archive") "Also generate an archive page listing every post grouped by year" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional math ("-mt", "--math") "Recognize $$display$$ and \\(inline\\) math plus math fenced blocks, rendered with KaTeX" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional lazy_images ("-li", "--lazy-images") "Have images load lazily and decode asynchronously" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional external_css ("-ec", "--external-css") "Write the stylesheet once as style.css and link it rather than inlining it into every page" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional flat_html ("-fh", "--flat-html") "Write each post as <url_name>.html instead of <url_name>/index.html, for hosts without directory indexes" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional validate_assets ("-va", "--validate-assets") "Warn about local favicon, social and post images which do not exist" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional check_links ("-ck", "--check-links") "Warn about relative and site root links which do not lead to any generated page or copied file" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional hash_assets ("-ha", "--hash-assets") "Name the --external-css stylesheet after a hash of its contents so browsers refetch it whenever it changes" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional sanitize_html ("-sh", "--sanitize-html") "Strip scripts, event handlers, unsafe URLs and unknown elements from raw HTML in posts" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional emoji ("-em", "--emoji") "Replace known :shortcode: emoji in post text with their Unicode character" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional heading_links ("-hl", "--heading-links") "Give the permalink of each h2 and h3 a data-clipboard attribute holding its full URL" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional minify ("-x", "--minify") "Minify generated HTML pages and their inline CSS" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional pretty ("-pt", "--pretty") "Indent generated HTML pages for reading, leaving <pre> contents untouched" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional rewrite_links ("-r", "--rewrite-links") "Prefix relative links and images with the base URL and post folder" -> bool {
+		without_arg() {
+			true
+		}
+	},
+
+	optional page_size ("-p", "--page-size") "Split the blog list into pages of this many entries" -> usize {
+		with_arg(size) {
+			match size.to_string_lossy().parse() {
+				Ok(size) if size > 0 => size,
+				_ => arg_parse_error!("Page size must be a positive integer, got '{}'", size.to_string_lossy()),
+			}
+		}
+	},
+
+	optional feed_limit ("-fl", "--feed-limit") "Only include this many of the newest posts in each feed" -> usize {
+		with_arg(limit) {
+			match limit.to_string_lossy().parse() {
+				Ok(limit) if limit > 0 => limit,
+				_ => arg_parse_error!("Feed limit must be a positive integer, got '{}'", limit.to_string_lossy()),
+			}
+		}
+	},
+
+	optional precompress ("-pc", "--precompress") "Also write compressed siblings of text outputs, given as 'gzip', 'brotli' or 'gzip,brotli'" -> Precompress {
+		with_arg(formats) {
+			let mut precompress = Precompress::default();
+			for format in formats.to_string_lossy().split(',') {
+				match format.trim() {
+					"gzip" => precompress.gzip = true,
+					"brotli" => precompress.brotli = true,
+					format => arg_parse_error!("Unknown precompression format '{}'", format),
+				}
+			}
+			precompress
+		}
+	},
+
+	optional precompress_level ("-pl", "--precompress-level") "Compression level from 1 to 9 for --precompress, defaults to 9" -> u32 {
+		with_arg(level) {
+			match level.to_string_lossy().parse() {
+				Ok(level) if (1..=9).contains(&level) => level,
+				_ => arg_parse_error!("Precompress level must be from 1 to 9, got '{}'", level.to_string_lossy()),
+			}
+		}
+	},
+
+	optional reading_speed ("-rs", "--reading-speed") "Words read per minute when estimating reading time, defaults to 200" -> usize {
+		with_arg(speed) {
+			match speed.to_string_lossy().parse() {
+				Ok(speed) if speed > 0 => speed,
+				_ => arg_parse_error!("Reading speed must be a positive integer, got '{}'", speed.to_string_lossy()),
+			}
+		}
+	},
+
+	optional feed_ttl ("-ft", "--feed-ttl") "Minutes feed readers should wait between polls of the RSS feeds" -> u32 {
+		with_arg(minutes) {
+			match minutes.to_string_lossy().parse() {
+				Ok(minutes) => minutes,
+				_ => arg_parse_error!("Feed TTL must be a whole number of minutes, got '{}'", minutes.to_string_lossy()),
+			}
+		}
+	},
+
+	optional list_limit ("-ll", "--list-limit") "Only list this many of the newest posts on the index and tag pages" -> usize {
+		with_arg(limit) {
+			match limit.to_string_lossy().parse() {
+				Ok(limit) if limit > 0 => limit,
+				_ => arg_parse_error!("List limit must be a positive integer, got '{}'", limit.to_string_lossy()),
+			}
+		}
+	},
+
+	optional source_date ("-sde", "--source-date") "Unix timestamp to stamp generated files with instead of the current time, also read from SOURCE_DATE_EPOCH" -> DateTime<Utc> {
+		with_arg(epoch) {
+			match parse_epoch(&epoch.to_string_lossy()) {
+				Some(date) => date,
+				None => arg_parse_error!("Source date must be a Unix timestamp, got '{}'", epoch.to_string_lossy()),
+			}
+		}
+	},
+
+	optional date_format ("-df", "--date-format") "chrono format string for post date attributes, defaults to '%d %b %Y %H:%M:%S %z'" -> String {
+		with_arg(format) {
+			format.to_string_lossy().into()
+		}
+	},
+
+	optional timezone ("-tz", "--timezone") "Offset such as '+02:00' or IANA zone name such as 'Europe/Berlin' for post dates written without an offset" -> Timezone {
+		with_arg(timezone) {
+			match parse_timezone(&timezone.to_string_lossy()) {
+				Some(timezone) => timezone,
+				None => arg_parse_error!("Unknown timezone '{}'", timezone.to_string_lossy()),
+			}
+		}
+	},
+
+	optional display_date_format ("-dd", "--display-date-format") "chrono format string for displayed dates, ordinal suffixes are only added by the default" -> String {
+		with_arg(format) {
+			let format = format.to_string_lossy().to_string();
+			if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+				arg_parse_error!("Invalid display date format '{}'", format);
+			}
+			format
+		}
+	},
+
+	optional list_order ("-lo", "--list-order") "Order of posts on the index and tag pages, 'desc' for newest first (the default) or 'asc'" -> String {
+		with_arg(order) {
+			match order.to_str() {
+				Some(order @ "asc") | Some(order @ "desc") => order.to_string(),
+				_ => arg_parse_error!("Unknown list order '{}'", order.to_string_lossy()),
+			}
+		}
+	},
+
+	optional base_path ("-b", "--base-path") "Path prefix for site local links when hosted in a subdirectory, unlike --base-url never used for absolute feed links" -> String {
+		with_arg(path) {
+			path.to_string_lossy().into()
+		}
+	},
+
+	optional twitter_card ("-t", "--twitter-card") "Emit Twitter Card metadata with this card type, 'summary' or 'summary_large_image'" -> String {
+		with_arg(card) {
+			match card.to_str() {
+				Some(card @ "summary") | Some(card @ "summary_large_image") => card.to_string(),
+				_ => arg_parse_error!("Unknown Twitter card type '{}'", card.to_string_lossy()),
+			}
+		}
+	},
+
+	optional serve_address ("-sa", "--serve-address") "Address for --serve to listen on, defaults to localhost:8080" -> String {
+		with_arg(address) {
+			address.to_string_lossy().into()
+		}
+	},
+
+	optional assets_dir ("-as", "--assets-dir") "Directory whose contents are copied as is into the output root, for site wide files" -> PathBuf {
+		with_arg(dir) {
+			dir.into()
+		}
+	},
+
 	optional fragments_dir ("-f", "--fragments") "Directory to retrieve html footer/header/ect fragments from" -> PathBuf {
 		with_arg(dir) {
 			dir.into()
 		}
 	},
 
-	required blog_base_url ("-u", "--base-url") "Base URL for blog subfolder" -> String {
-		with_arg(url) {
-			url.to_string_lossy().into()
+	repeated callouts ("-co", "--callout") "Render fenced blocks of language KIND as a div of class CLASS, given as 'KIND:CLASS'" -> (String, String), min 0 {
+		with_arg(callout) {
+			match callout.to_string_lossy().split_once(':') {
+				Some((kind, class)) if !kind.is_empty() && !class.is_empty() => {
+					(kind.to_string(), class.to_string())
+				}
+				_ => arg_parse_error!("Callout must be given as 'KIND:CLASS', got '{}'", callout.to_string_lossy()),
+			}
 		}
 	},
 
-	required input_dir ("-i", "--input") "Input directory to scan for .md files and assets" -> PathBuf {
+	repeated feed_configs ("-fc", "--feed-config") "Only include this many of the newest posts in the additional feed NAME, given as 'NAME:LIMIT'" -> (String, usize), min 0 {
+		with_arg(config) {
+			let config = config.to_string_lossy();
+			let parsed = config
+				.split_once(':')
+				.and_then(|(name, limit)| Some((name, limit.parse().ok()?)));
+			match parsed {
+				Some((name, limit)) if !name.is_empty() && limit > 0 => (name.to_string(), limit),
+				_ => arg_parse_error!("Feed config must be given as 'NAME:LIMIT', got '{}'", config),
+			}
+		}
+	},
+
+	repeated input_dirs ("-i", "--input") "Input directory to scan for .md files and assets, may be given multiple times" -> PathBuf, min 1 {
 		with_arg(dir) {
 			dir.into()
 		}
 	},
 
-	required output_dir ("-o", "--output") "Directory to place output files *DESTRUCTIVE, WILL DELETE ORIGINAL FOLDER CONTENTS*" -> PathBuf {
+	required blog_base_url ("-u", "--base-url") "Base URL for blog subfolder" -> String {
+		with_arg(url) {
+			url.to_string_lossy().into()
+		}
+	},
+
+	required output_dir ("-o", "--output") "Directory to place output files, its existing contents are only deleted when --clean is passed" -> PathBuf {
 		with_arg(dir) {
 			dir.into()
 		}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::arguments::Arguments;
+use crate::error::BuildError;
+use crate::{output, stable_hash, BlogEntry, Fragments};
+
+const MANIFEST_NAME: &str = ".floc-cache.json";
+
+#[derive(Serialize, Deserialize)]
+struct CachedPost {
+	modified: u64,
+	hash: u64,
+	entry: BlogEntry,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+	fingerprint: u64,
+	posts: HashMap<String, CachedPost>,
+}
+
+/*
+ * Remembers rendered posts between `--incremental` builds. A post is reused
+ * while its markdown is unchanged, but the manifest is thrown away entirely
+ * as soon as the fragments or flags it was built with differ in any way.
+ */
+pub struct Cache {
+	enabled: bool,
+	fingerprint: u64,
+	previous: HashMap<String, CachedPost>,
+	current: Mutex<HashMap<String, CachedPost>>,
+}
+
+pub fn modified(path: &Path) -> u64 {
+	let modified = path.metadata().and_then(|metadata| metadata.modified());
+	let since_epoch = modified
+		.ok()
+		.and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+	since_epoch.map_or(0, |since_epoch| since_epoch.as_nanos() as u64)
+}
+
+//Kept out of the output directory as the manifest holds every rendered post in full
+fn manifest_path(args: &Arguments) -> PathBuf {
+	match &args.cache_path {
+		Some(cache_path) => cache_path.clone(),
+		None => PathBuf::from(MANIFEST_NAME),
+	}
+}
+
+impl Cache {
+	pub fn load(args: &Arguments, fragments: &Fragments) -> Cache {
+		let enabled = args.incremental.unwrap_or(false);
+		let fingerprint = stable_hash(&format!("{} {:?} {:?}", crate::VERSION, args, fragments));
+
+		//A clean build is always a full one even though the manifest lives elsewhere
+		let mut previous = HashMap::new();
+		if enabled && !args.clean.unwrap_or(false) {
+			//A missing or unreadable manifest only means everything gets rendered
+			let contents = std::fs::read_to_string(manifest_path(args));
+			let manifest = contents
+				.ok()
+				.and_then(|contents| serde_json::from_str::<Manifest>(&contents).ok());
+
+			if let Some(manifest) = manifest {
+				if manifest.fingerprint == fingerprint {
+					previous = manifest.posts;
+				}
+			}
+		}
+
+		Cache {
+			enabled,
+			fingerprint,
+			previous,
+			current: Mutex::new(HashMap::new()),
+		}
+	}
+
+	//Only hashes the contents when the modification time alone cannot vouch for them
+	pub fn reuse(&self, path: &Path, modified: u64, contents: &str) -> Option<BlogEntry> {
+		let cached = self.previous.get(path.to_string_lossy().as_ref())?;
+		if cached.modified != modified && cached.hash != stable_hash(contents) {
+			return None;
+		}
+
+		let entry = cached.entry.clone();
+		self.record(path, modified, contents, &entry);
+		Some(entry)
+	}
+
+	pub fn record(&self, path: &Path, modified: u64, contents: &str, entry: &BlogEntry) {
+		if !self.enabled {
+			return;
+		}
+
+		let cached = CachedPost {
+			modified,
+			hash: stable_hash(contents),
+			entry: entry.clone(),
+		};
+
+		let mut current = self.current.lock().expect("Cache lock was poisoned");
+		current.insert(path.to_string_lossy().into_owned(), cached);
+	}
+
+	pub fn save(self, args: &Arguments) -> Result<(), BuildError> {
+		if !self.enabled {
+			return Ok(());
+		}
+
+		let manifest = Manifest {
+			fingerprint: self.fingerprint,
+			posts: self.current.into_inner().expect("Cache lock was poisoned"),
+		};
+		let json = serde_json::to_string(&manifest).expect("Failed to serialize cache manifest");
+
+		let path = manifest_path(args);
+		if let Err(err) = output::write_private(args, &path, json.as_bytes()) {
+			return Err(BuildError::WriteCache { path, err });
+		}
+
+		Ok(())
+	}
+}
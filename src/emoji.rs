@@ -0,0 +1,176 @@
+use std::borrow::Cow;
+
+//Sorted by shortcode so lookups can binary search
+const SHORTCODES: &[(&str, &str)] = &[
+	("+1", "👍"),
+	("-1", "👎"),
+	("100", "💯"),
+	("angry", "😠"),
+	("apple", "🍎"),
+	("arrow_down", "⬇️"),
+	("arrow_left", "⬅️"),
+	("arrow_right", "➡️"),
+	("arrow_up", "⬆️"),
+	("baby", "👶"),
+	("balloon", "🎈"),
+	("bangbang", "‼️"),
+	("beer", "🍺"),
+	("bell", "🔔"),
+	("bird", "🐦"),
+	("blush", "😊"),
+	("bomb", "💣"),
+	("book", "📖"),
+	("books", "📚"),
+	("boom", "💥"),
+	("bug", "🐛"),
+	("bulb", "💡"),
+	("cake", "🍰"),
+	("calendar", "📆"),
+	("camera", "📷"),
+	("cat", "🐱"),
+	("check", "✔️"),
+	("clap", "👏"),
+	("clipboard", "📋"),
+	("clock", "🕐"),
+	("cloud", "☁️"),
+	("coffee", "☕"),
+	("computer", "💻"),
+	("confused", "😕"),
+	("construction", "🚧"),
+	("cookie", "🍪"),
+	("cool", "🆒"),
+	("crab", "🦀"),
+	("cry", "😢"),
+	("dog", "🐶"),
+	("earth_americas", "🌎"),
+	("envelope", "✉️"),
+	("exclamation", "❗"),
+	("eyes", "👀"),
+	("fire", "🔥"),
+	("flushed", "😳"),
+	("gear", "⚙️"),
+	("gift", "🎁"),
+	("grin", "😁"),
+	("grinning", "😀"),
+	("hammer", "🔨"),
+	("heart", "❤️"),
+	("heart_eyes", "😍"),
+	("heavy_check_mark", "✔️"),
+	("hourglass", "⌛"),
+	("house", "🏠"),
+	("hugs", "🤗"),
+	("information_source", "ℹ️"),
+	("joy", "😂"),
+	("key", "🔑"),
+	("kissing", "😗"),
+	("laughing", "😆"),
+	("link", "🔗"),
+	("lock", "🔒"),
+	("mag", "🔍"),
+	("memo", "📝"),
+	("moon", "🌙"),
+	("muscle", "💪"),
+	("music", "🎵"),
+	("neutral_face", "😐"),
+	("no_entry", "⛔"),
+	("ok_hand", "👌"),
+	("package", "📦"),
+	("paperclip", "📎"),
+	("party", "🥳"),
+	("pencil", "📝"),
+	("penguin", "🐧"),
+	("pizza", "🍕"),
+	("point_down", "👇"),
+	("point_left", "👈"),
+	("point_right", "👉"),
+	("point_up", "☝️"),
+	("pray", "🙏"),
+	("question", "❓"),
+	("rainbow", "🌈"),
+	("raised_hands", "🙌"),
+	("recycle", "♻️"),
+	("relaxed", "☺️"),
+	("rocket", "🚀"),
+	("rofl", "🤣"),
+	("rose", "🌹"),
+	("sad", "😞"),
+	("scream", "😱"),
+	("see_no_evil", "🙈"),
+	("shrug", "🤷"),
+	("skull", "💀"),
+	("sleeping", "😴"),
+	("smile", "😄"),
+	("smiley", "😃"),
+	("smirk", "😏"),
+	("snake", "🐍"),
+	("snowflake", "❄️"),
+	("sob", "😭"),
+	("sparkles", "✨"),
+	("star", "⭐"),
+	("sunglasses", "😎"),
+	("sunny", "☀️"),
+	("sweat_smile", "😅"),
+	("tada", "🎉"),
+	("thinking", "🤔"),
+	("thumbsdown", "👎"),
+	("thumbsup", "👍"),
+	("trophy", "🏆"),
+	("unamused", "😒"),
+	("unlock", "🔓"),
+	("warning", "⚠️"),
+	("wave", "👋"),
+	("white_check_mark", "✅"),
+	("wink", "😉"),
+	("wrench", "🔧"),
+	("x", "❌"),
+	("zap", "⚡"),
+	("zzz", "💤"),
+];
+
+fn lookup(shortcode: &str) -> Option<&'static str> {
+	let index = SHORTCODES
+		.binary_search_by(|(candidate, _)| candidate.cmp(&shortcode))
+		.ok()?;
+	Some(SHORTCODES[index].1)
+}
+
+fn is_shortcode_char(c: char) -> bool {
+	c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-'
+}
+
+//Unknown shortcodes are left exactly as written, colons and all
+pub fn expand(text: &str) -> Cow<'_, str> {
+	if !text.contains(':') {
+		return Cow::Borrowed(text);
+	}
+
+	let mut expanded = String::with_capacity(text.len());
+	let mut rest = text;
+
+	while let Some(start) = rest.find(':') {
+		expanded.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let name_length = rest[1..]
+			.find(|c: char| !is_shortcode_char(c))
+			.unwrap_or(rest.len() - 1);
+		let name = &rest[1..1 + name_length];
+		let closed = rest[1 + name_length..].starts_with(':');
+
+		match lookup(name).filter(|_| closed && !name.is_empty()) {
+			Some(emoji) => {
+				expanded.push_str(emoji);
+				rest = &rest[name_length + 2..];
+			}
+
+			//The closing colon may well open the next shortcode so only the first is consumed
+			None => {
+				expanded.push(':');
+				rest = &rest[1..];
+			}
+		}
+	}
+
+	expanded.push_str(rest);
+	Cow::Owned(expanded)
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum BuildError {
+	LoadFragment {
+		name: String,
+		err: io::Error,
+	},
+	MissingAttribute {
+		path: PathBuf,
+		attribute: &'static str,
+	},
+	ParseDate {
+		path: PathBuf,
+		format: String,
+		err: chrono::ParseError,
+	},
+	NonexistentDate {
+		path: PathBuf,
+		date: String,
+	},
+	ParseFrontMatter {
+		path: PathBuf,
+		err: serde_yaml::Error,
+	},
+	InvalidSlug {
+		path: PathBuf,
+		slug: String,
+	},
+	MissingTemplateKey {
+		key: String,
+	},
+	OpenInputDir {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WalkInputDir {
+		err: io::Error,
+	},
+	IndexNamedFile {
+		path: PathBuf,
+	},
+	DuplicatePost {
+		url_name: String,
+		first: PathBuf,
+		second: PathBuf,
+	},
+	OpenDir {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WalkDir {
+		path: PathBuf,
+		err: io::Error,
+	},
+	MissingFileName {
+		path: PathBuf,
+	},
+	MultipleMarkdown {
+		first: PathBuf,
+		second: PathBuf,
+	},
+	CopyFile {
+		from: PathBuf,
+		to: PathBuf,
+		err: io::Error,
+	},
+	ReadFile {
+		path: PathBuf,
+		err: io::Error,
+	},
+	ReadMarkdown {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteHtml {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteFeed {
+		kind: &'static str,
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteTagList {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteBlogList {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteSitemap {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteArchive {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteSearchIndex {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteRobots {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteStylesheet {
+		path: PathBuf,
+		err: io::Error,
+	},
+	WriteCache {
+		path: PathBuf,
+		err: io::Error,
+	},
+}
+
+impl fmt::Display for BuildError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use BuildError::*;
+
+		match self {
+			LoadFragment { name, err } => write!(f, "Error loading fragment '{}': {}", name, err),
+
+			MissingAttribute { path, attribute } => write!(
+				f,
+				"Error input file '{}' is missing {} attribute",
+				path.to_string_lossy(),
+				attribute
+			),
+
+			ParseDate { path, format, err } => write!(
+				f,
+				"Error parsing date attribute in input file '{}', expected format '{}': {}",
+				path.to_string_lossy(),
+				format,
+				err
+			),
+
+			NonexistentDate { path, date } => write!(
+				f,
+				"Error date '{}' in input file '{}' does not exist in the given timezone",
+				date,
+				path.to_string_lossy()
+			),
+
+			InvalidSlug { path, slug } => write!(
+				f,
+				"Error slug '{}' in input file '{}' must not contain '..', '\\' or empty segments",
+				slug,
+				path.to_string_lossy()
+			),
+
+			ParseFrontMatter { path, err } => write!(
+				f,
+				"Error parsing front matter in input file '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			MissingTemplateKey { key } => {
+				write!(f, "Error failed to template substitute for key '{}'", key)
+			}
+
+			OpenInputDir { path, err } => write!(
+				f,
+				"Error opening input dir '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WalkInputDir { err } => write!(f, "Error walking input dir: {}", err),
+
+			IndexNamedFile { path } => write!(
+				f,
+				"Error, file '{}' should not be named 'index.*'",
+				path.to_string_lossy()
+			),
+
+			DuplicatePost {
+				url_name,
+				first,
+				second,
+			} => write!(
+				f,
+				"Error, post '{}' exists in both '{}' and '{}'",
+				url_name,
+				first.to_string_lossy(),
+				second.to_string_lossy()
+			),
+
+			OpenDir { path, err } => {
+				write!(f, "Error opening dir '{}': {}", path.to_string_lossy(), err)
+			}
+
+			WalkDir { path, err } => {
+				write!(f, "Error walking dir '{}': {}", path.to_string_lossy(), err)
+			}
+
+			MissingFileName { path } => {
+				write!(f, "Failed to get filename for '{}'", path.to_string_lossy())
+			}
+
+			MultipleMarkdown { first, second } => write!(
+				f,
+				"Error, post folder holds more than one markdown file, '{}' and '{}'",
+				first.to_string_lossy(),
+				second.to_string_lossy()
+			),
+
+			CopyFile { from, to, err } => write!(
+				f,
+				"Error copying input file '{}' to '{}': {}",
+				from.to_string_lossy(),
+				to.to_string_lossy(),
+				err
+			),
+
+			ReadFile { path, err } => write!(
+				f,
+				"Error reading input file '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			ReadMarkdown { path, err } => write!(
+				f,
+				"Error reading input markdown file '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteHtml { path, err } => write!(
+				f,
+				"Error writing HTML to path '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteFeed { kind, path, err } => write!(
+				f,
+				"Error writing {} feed file '{}': {}",
+				kind,
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteTagList { path, err } => write!(
+				f,
+				"Error writing tag list '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteBlogList { path, err } => write!(
+				f,
+				"Error writing blog entry list '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteSitemap { path, err } => write!(
+				f,
+				"Error writing sitemap '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteArchive { path, err } => write!(
+				f,
+				"Error writing archive '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteSearchIndex { path, err } => write!(
+				f,
+				"Error writing search index '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteRobots { path, err } => write!(
+				f,
+				"Error writing robots.txt '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteStylesheet { path, err } => write!(
+				f,
+				"Error writing stylesheet '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+
+			WriteCache { path, err } => write!(
+				f,
+				"Error writing incremental build cache '{}': {}",
+				path.to_string_lossy(),
+				err
+			),
+		}
+	}
+}
+
+impl std::error::Error for BuildError {}
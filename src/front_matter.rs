@@ -0,0 +1,69 @@
+use serde_yaml::{Mapping, Value};
+
+pub fn split(input: &str) -> Option<(&str, &str)> {
+	let rest = input.strip_prefix("---")?;
+	let rest = rest
+		.strip_prefix("\r\n")
+		.or_else(|| rest.strip_prefix('\n'))?;
+
+	let mut offset = 0;
+	for line in rest.split_inclusive('\n') {
+		if line.trim_end() == "---" {
+			let yaml = &rest[..offset];
+			let markdown = &rest[offset + line.len()..];
+			return Some((yaml, markdown));
+		}
+
+		offset += line.len();
+	}
+
+	None
+}
+
+/*
+ * Flattens the front matter into the same label/value pairs
+ * the HTML comments produce. A list yields one pair per item
+ * so `tags` and `additional-feed` can be given as YAML lists.
+ */
+pub fn parse(yaml: &str) -> Result<Vec<(String, String)>, serde_yaml::Error> {
+	if yaml.trim().is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let mapping: Mapping = serde_yaml::from_str(yaml)?;
+
+	fn scalar_to_string(value: &Value) -> Option<String> {
+		match value {
+			Value::String(value) => Some(value.clone()),
+			Value::Number(value) => Some(value.to_string()),
+			Value::Bool(value) => Some(value.to_string()),
+			_ => None,
+		}
+	}
+
+	let mut fields = Vec::new();
+	for (key, value) in &mapping {
+		let key = match scalar_to_string(key) {
+			Some(key) => key,
+			None => continue,
+		};
+
+		match value {
+			Value::Sequence(items) => {
+				for item in items {
+					if let Some(item) = scalar_to_string(item) {
+						fields.push((key.clone(), item));
+					}
+				}
+			}
+
+			value => {
+				if let Some(value) = scalar_to_string(value) {
+					fields.push((key, value));
+				}
+			}
+		}
+	}
+
+	Ok(fields)
+}
@@ -0,0 +1,51 @@
+use crate::arguments::Arguments;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+	Quiet,
+	Normal,
+	Verbose,
+}
+
+impl Arguments {
+	//Quiet wins over verbose so that scripts can always silence a build
+	pub fn log_level(&self) -> LogLevel {
+		if self.quiet.unwrap_or(false) {
+			LogLevel::Quiet
+		} else if self.verbose.unwrap_or(false) {
+			LogLevel::Verbose
+		} else {
+			LogLevel::Normal
+		}
+	}
+}
+
+/*
+ * NOTE: Fatal errors are deliberately not routed through here, they
+ * are always printed no matter the log level as `--quiet` would
+ * otherwise leave a failed build with no explanation at all.
+ */
+
+macro_rules! info {
+	( $args:expr, $($arg:tt)* ) => {
+		if $args.log_level() >= crate::log::LogLevel::Normal {
+			println!($($arg)*);
+		}
+	};
+}
+
+macro_rules! verbose {
+	( $args:expr, $($arg:tt)* ) => {
+		if $args.log_level() >= crate::log::LogLevel::Verbose {
+			println!($($arg)*);
+		}
+	};
+}
+
+macro_rules! warning {
+	( $args:expr, $($arg:tt)* ) => {
+		if $args.log_level() >= crate::log::LogLevel::Normal {
+			eprintln!($($arg)*);
+		}
+	};
+}
@@ -1,22 +1,56 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt::Write;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use chrono::{DateTime, Datelike, Utc};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 
 use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
 
+use rayon::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+#[macro_use]
+mod log;
+
 mod arguments;
+mod cache;
+mod emoji;
+mod error;
+mod front_matter;
+mod math;
+mod minify;
+mod output;
+mod pretty;
+mod sanitize;
+#[cfg(feature = "serve")]
+mod serve;
 mod template;
+mod watch;
 
-use arguments::Arguments;
-use template::format_template;
+use arguments::{Arguments, Timezone};
+use cache::Cache;
+use error::BuildError;
+use sanitize::Sanitizer;
+use template::{escape_html, format_template};
 
 pub const VERSION: &str = "0.0.1";
 
+const DEFAULT_DATE_FORMAT: &str = "%d %b %Y %H:%M:%S %z";
+
+const DEFAULT_READING_SPEED: usize = 200;
+
+const TOC_MARKER: &str = "<!--toc-->";
+
+//Everything before this is the excerpt shown in lists, on the post it becomes a jump target
+const MORE_MARKER: &str = "<!--more-->";
+const MORE_ANCHOR: &str = r#"<a id="more"></a>"#;
+
 macro_rules! multiline {
 	( $($line:expr)* ) => {
 		concat!( $($line, "\n"),* )
@@ -58,13 +92,46 @@ impl FeedTracker {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct BlogEntry {
 	url_name: String,
 	title: String,
 	description: String,
+	summary: String,
 	date: DateTime<Utc>,
+	updated: Option<DateTime<Utc>>,
+	feed_names: Vec<String>,
+	#[serde(skip)]
 	additional_feeds: Vec<u32>,
+	tags: Vec<String>,
+	draft: bool,
+	unlisted: bool,
+	featured: bool,
+	word_count: usize,
+	reading_minutes: usize,
+	body: String,
+	excerpt: Option<String>,
+	image: Option<String>,
+	author: Option<String>,
+
+	//Where the post came from and the images it shows, for `--validate-assets`
+	source_dir: PathBuf,
+	images: Vec<String>,
+
+	//Internal link destinations as written, for `--check-links`
+	links: Vec<String>,
+
+	//Language code and url name of each variant of this post, itself included
+	translations: Vec<(String, String)>,
+
+	/*
+	 * The rendered page is kept in pieces until every post is known, `head`
+	 * being the contents of `<head>`, `header` the templated header fragment
+	 * and `footer` the untemplated fragment.
+	 */
+	head: String,
+	header: String,
+	footer: String,
 }
 
 #[derive(Debug)]
@@ -74,52 +141,266 @@ struct Fragments {
 	footer: String,
 	blog_entry: String,
 	blog_list: String,
+	not_found: Option<String>,
+
+	//Every other `.html` file keyed by file stem, for per-post templates
+	extra: HashMap<String, String>,
 }
 
 impl Fragments {
-	fn retrieve_or_shim(dir: Option<PathBuf>) -> Fragments {
+	fn retrieve_or_shim(dir: Option<PathBuf>) -> Result<Fragments, BuildError> {
 		let mut dir = match dir {
 			Some(dir) => dir,
 
 			None => {
-				return Fragments {
+				return Ok(Fragments {
 					css: String::new(),
 					header: String::new(),
 					footer: String::new(),
 					blog_entry: String::new(),
 					blog_list: String::new(),
-				};
+					not_found: None,
+					extra: HashMap::new(),
+				});
 			}
 		};
 
-		fn get_fragment(dir: &mut PathBuf, name: &str) -> String {
+		fn get_fragment(dir: &mut PathBuf, name: &str) -> Result<String, BuildError> {
 			dir.push(name);
 
 			let fragment = match std::fs::read_to_string(&dir) {
 				Ok(fragment) => fragment.trim().to_string(),
 
 				Err(err) => {
-					eprintln!("Error loading fragment '{}': {}", name, err);
-					std::process::exit(-1);
+					let name = name.to_string();
+					return Err(BuildError::LoadFragment { name, err });
 				}
 			};
 
 			dir.pop();
-			fragment
+			Ok(fragment)
 		}
 
-		let css = get_fragment(&mut dir, "style.css");
-		let header = get_fragment(&mut dir, "header.html");
-		let footer = get_fragment(&mut dir, "footer.html");
-		let blog_entry = get_fragment(&mut dir, "blog_entry.html");
-		let blog_list = get_fragment(&mut dir, "blog_list.html");
+		let css = get_fragment(&mut dir, "style.css")?;
+		let header = get_fragment(&mut dir, "header.html")?;
+		let footer = get_fragment(&mut dir, "footer.html")?;
+		let blog_entry = get_fragment(&mut dir, "blog_entry.html")?;
+		let blog_list = get_fragment(&mut dir, "blog_list.html")?;
 
-		Fragments {
+		const STANDARD: &[&str] = &["header", "footer", "blog_entry", "blog_list"];
+
+		let mut extra = HashMap::new();
+		let entries = match std::fs::read_dir(&dir) {
+			Ok(entries) => entries,
+			Err(err) => return Err(BuildError::OpenDir { path: dir, err }),
+		};
+		for entry in entries {
+			let path = match entry {
+				Ok(entry) => entry.path(),
+				Err(err) => return Err(BuildError::WalkDir { path: dir, err }),
+			};
+
+			if path.extension().map(|e| e.to_str()) != Some(Some("html")) {
+				continue;
+			}
+
+			let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+				Some(stem) if !STANDARD.contains(&stem) => stem.to_string(),
+				_ => continue,
+			};
+
+			let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+			let fragment = get_fragment(&mut dir, &file_name)?;
+			extra.insert(stem, fragment);
+		}
+
+		//Optional, without it no 404 page is written
+		let not_found = extra.remove("not_found");
+
+		Ok(Fragments {
 			css,
 			header,
 			footer,
 			blog_entry,
 			blog_list,
+			not_found,
+			extra,
+		})
+	}
+
+	//`<template>_<kind>.html` if the post named a template and it exists, else the default
+	fn for_template<'a>(&'a self, template: &str, kind: &str) -> &'a str {
+		let default = match kind {
+			"header" => &self.header,
+			"footer" => &self.footer,
+			_ => unreachable!("Unknown fragment kind '{}'", kind),
+		};
+
+		if template.is_empty() {
+			return default;
+		}
+
+		match self.extra.get(&format!("{}_{}", template, kind)) {
+			Some(fragment) => fragment,
+			None => default,
+		}
+	}
+}
+
+struct Metadata {
+	title: String,
+	description: String,
+	summary: String,
+	author: String,
+	date: String,
+	updated: String,
+	additional_feeds: Vec<String>,
+	tags: Vec<String>,
+	draft: bool,
+	unlisted: bool,
+	featured: bool,
+	template: String,
+	image: String,
+	canonical: String,
+	robots: String,
+	translations: Vec<(String, String)>,
+	slug: String,
+}
+
+impl Metadata {
+	fn new() -> Metadata {
+		Metadata {
+			title: String::new(),
+			description: String::new(),
+			summary: String::new(),
+			author: String::new(),
+			date: String::new(),
+			updated: String::new(),
+			additional_feeds: Vec::new(),
+			tags: Vec::new(),
+			draft: false,
+			unlisted: false,
+			featured: false,
+			template: String::new(),
+			image: String::new(),
+			canonical: String::new(),
+			robots: String::new(),
+			translations: Vec::new(),
+			slug: String::new(),
+		}
+	}
+
+	fn clear(&mut self) {
+		self.title.clear();
+		self.description.clear();
+		self.summary.clear();
+		self.author.clear();
+		self.date.clear();
+		self.updated.clear();
+		self.additional_feeds.clear();
+		self.tags.clear();
+		self.draft = false;
+		self.unlisted = false;
+		self.featured = false;
+		self.template.clear();
+		self.image.clear();
+		self.canonical.clear();
+		self.robots.clear();
+		self.translations.clear();
+		self.slug.clear();
+	}
+
+	//Shared by the HTML comment labels and the YAML front matter keys
+	fn apply(&mut self, label: &str, value: &str) {
+		match label {
+			"title" => {
+				self.title.clear();
+				self.title.push_str(value);
+			}
+
+			"description" => {
+				self.description.clear();
+				self.description.push_str(value);
+			}
+
+			"summary" => {
+				self.summary.clear();
+				self.summary.push_str(value);
+			}
+
+			"author" => {
+				self.author.clear();
+				self.author.push_str(value);
+			}
+
+			"date" => {
+				self.date.clear();
+				self.date.push_str(value);
+			}
+
+			"updated" => {
+				self.updated.clear();
+				self.updated.push_str(value);
+			}
+
+			"additional-feed" => {
+				self.additional_feeds.push(value.to_string());
+			}
+
+			"tags" => {
+				let names = value.split(',').map(str::trim);
+				let names = names.filter(|name| !name.is_empty()).map(String::from);
+				self.tags.extend(names);
+			}
+
+			"draft" => {
+				self.draft = value == "true";
+			}
+
+			"unlisted" => {
+				self.unlisted = value == "true";
+			}
+
+			"featured" => {
+				self.featured = value == "true";
+			}
+
+			"template" => {
+				self.template.clear();
+				self.template.push_str(value);
+			}
+
+			"image" => {
+				self.image.clear();
+				self.image.push_str(value);
+			}
+
+			"canonical" => {
+				self.canonical.clear();
+				self.canonical.push_str(value);
+			}
+
+			"robots" => {
+				self.robots.clear();
+				self.robots.push_str(value);
+			}
+
+			"translations" => {
+				let pairs = value.split(',').filter_map(|pair| pair.split_once(':'));
+				let pairs =
+					pairs.map(|(language, slug)| (language.trim(), slug.trim().trim_matches('/')));
+				let pairs =
+					pairs.filter(|(language, slug)| !language.is_empty() && !slug.is_empty());
+				let pairs = pairs.map(|(language, slug)| (language.to_string(), slug.to_string()));
+				self.translations.extend(pairs);
+			}
+
+			"slug" => {
+				self.slug.clear();
+				self.slug.push_str(value.trim_matches('/'));
+			}
+
+			_ => {}
 		}
 	}
 }
@@ -128,140 +409,571 @@ struct Buffers {
 	input: String,
 	html: String,
 	output: String,
+	metadata: Metadata,
+}
 
-	title: String,
-	description: String,
-	author: String,
-	date: String,
+impl Buffers {
+	fn new() -> Buffers {
+		Buffers {
+			input: String::new(),
+			html: String::new(),
+			output: String::new(),
+			metadata: Metadata::new(),
+		}
+	}
+}
+
+/*
+ * NOTE: A date carrying its own offset always wins. Only once that fails is
+ * it parsed again without the format's offset specifier and placed in the
+ * `--timezone`, the original error being reported if that fails too.
+ */
+fn parse_date(args: &Arguments, date: &str, path: &Path) -> Result<DateTime<Utc>, BuildError> {
+	let format = args.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+	let err = match DateTime::parse_from_str(date, format) {
+		Ok(date) => return Ok(DateTime::<Utc>::from(date)),
+		Err(err) => err,
+	};
+
+	if let Some(timezone) = args.timezone {
+		let naive_format = ["%:z", "%#z", "%z"]
+			.iter()
+			.fold(format.to_string(), |format, specifier| {
+				format.replace(specifier, "")
+			});
+		if let Ok(naive) = NaiveDateTime::parse_from_str(date.trim(), naive_format.trim()) {
+			//Times skipped over by a daylight saving change do not exist at all
+			let local = match timezone {
+				Timezone::Fixed(offset) => naive.and_local_timezone(offset).earliest(),
+				Timezone::Named(zone) => naive
+					.and_local_timezone(zone)
+					.earliest()
+					.map(|date| date.fixed_offset()),
+			};
+
+			return match local {
+				Some(local) => Ok(DateTime::<Utc>::from(local)),
+				None => {
+					let path = path.to_path_buf();
+					let date = date.to_string();
+					Err(BuildError::NonexistentDate { path, date })
+				}
+			};
+		}
+	}
+
+	let path = path.to_path_buf();
+	let format = format.to_string();
+	Err(BuildError::ParseDate { path, format, err })
+}
+
+//Posts are ordered by when they were published but an edit still counts as a modification
+fn last_modified(entry: &BlogEntry) -> DateTime<Utc> {
+	entry.updated.unwrap_or(entry.date)
 }
 
 fn build_blog_entry(
-	buffers: &Buffers,
+	args: &Arguments,
+	now: DateTime<Utc>,
+	metadata: &Metadata,
 	path: &Path,
 	url_name: &str,
-	additional_feeds: Vec<u32>,
-) -> BlogEntry {
-	fn check_error<'a>(text: &'a str, attribute: &str, path: &Path) -> &'a str {
+	word_count: usize,
+	body: String,
+) -> Result<BlogEntry, BuildError> {
+	fn check_error<'a>(
+		text: &'a str,
+		attribute: &'static str,
+		path: &Path,
+	) -> Result<&'a str, BuildError> {
 		if text.is_empty() {
-			eprintln!(
-				"Error input file '{}' is missing {} attribute",
-				path.to_string_lossy(),
-				attribute
-			);
-			std::process::exit(-1);
+			let path = path.to_path_buf();
+			Err(BuildError::MissingAttribute { path, attribute })
 		} else {
-			text
+			Ok(text)
 		}
 	}
 
-	let title = check_error(&buffers.title, "title", path).to_string();
-	let description = check_error(&buffers.description, "description", path).to_string();
+	let title = check_error(&metadata.title, "title", path)?.to_string();
+	let description = check_error(&metadata.description, "description", path)?.to_string();
 
-	let date = check_error(&buffers.date, "date", path);
-	let date = match DateTime::parse_from_str(date, "%d %b %Y %H:%M:%S %z") {
-		Ok(date) => date,
-		Err(err) => {
-			eprintln!(
-				"Error parsing date attribute in input file '{}': {}",
-				path.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
-		}
+	//Feeds and lists prefer the shorter summary, the page's own metadata keeps the description
+	let summary = if metadata.summary.is_empty() {
+		description.clone()
+	} else {
+		metadata.summary.clone()
 	};
 
-	BlogEntry {
+	let date = check_error(&metadata.date, "date", path)?;
+	let date = parse_date(args, date, path)?;
+
+	let mut updated = None;
+	if !metadata.updated.is_empty() {
+		updated = Some(parse_date(args, &metadata.updated, path)?);
+	}
+
+	if args.warn_future.unwrap_or(false) && date > now {
+		warning!(
+			args,
+			"Warning, post '{}' is dated in the future: {}",
+			url_name,
+			date.to_rfc2822()
+		);
+	}
+
+	Ok(BlogEntry {
 		url_name: url_name.to_string(),
 		title,
 		description,
-		date: date.into(),
-		additional_feeds,
+		summary,
+		date,
+		updated,
+		feed_names: metadata.additional_feeds.clone(),
+		additional_feeds: Vec::new(),
+		tags: metadata.tags.clone(),
+		draft: metadata.draft,
+		unlisted: metadata.unlisted,
+		featured: metadata.featured,
+		word_count,
+		reading_minutes: reading_minutes(args, word_count),
+		body,
+		excerpt: None,
+		image: Some(metadata.image.clone()).filter(|image| !image.is_empty()),
+		author: Some(metadata.author.clone()).filter(|author| !author.is_empty()),
+		source_dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+		images: Vec::new(),
+		links: Vec::new(),
+		translations: metadata.translations.clone(),
+		head: String::new(),
+		header: String::new(),
+		footer: String::new(),
+	})
+}
+
+//Renders every element `math::restore` produced once the page has loaded
+const KATEX_HEAD: &str = multiline!(
+	r#"<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.css">"#
+	r#"<script defer src="https://cdn.jsdelivr.net/npm/katex@0.16.9/dist/katex.min.js"></script>"#
+	r#"<script>"#
+	r#"document.addEventListener("DOMContentLoaded", function() {"#
+	r#"	for (const element of document.querySelectorAll(".math")) {"#
+	r#"		const displayMode = element.classList.contains("display");"#
+	r#"		katex.render(element.textContent, element, { displayMode, throwOnError: false });"#
+	r#"	}"#
+	r#"});"#
+	r#"</script>"#
+);
+
+const AUTO_DESCRIPTION_LENGTH: usize = 160;
+
+fn derive_description(text: &str) -> String {
+	let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+	if text.chars().count() <= AUTO_DESCRIPTION_LENGTH {
+		return text;
 	}
+
+	let cut = text
+		.char_indices()
+		.nth(AUTO_DESCRIPTION_LENGTH)
+		.map_or(text.len(), |(index, _)| index);
+	let truncated = &text[..cut];
+
+	//Back up to the last whole word so the description never ends mid word
+	let truncated = match truncated.rfind(' ') {
+		Some(space_index) => &truncated[..space_index],
+		None => truncated,
+	};
+
+	format!(
+		"{}…",
+		truncated.trim_end_matches(|c: char| c.is_ascii_punctuation())
+	)
 }
 
 fn process_markdown(
 	args: &Arguments,
+	now: DateTime<Utc>,
 	path: &Path,
 	url_name: &str,
-	feed_tracker: &mut FeedTracker,
 	fragments: &Fragments,
 	buffers: &mut Buffers,
-) -> BlogEntry {
+) -> Result<BlogEntry, BuildError> {
+	buffers.metadata.clear();
+
+	let (markdown, has_front_matter) = match front_matter::split(&buffers.input) {
+		Some((yaml, markdown)) => {
+			let fields = match front_matter::parse(yaml) {
+				Ok(fields) => fields,
+				Err(err) => {
+					let path = path.to_path_buf();
+					return Err(BuildError::ParseFrontMatter { path, err });
+				}
+			};
+
+			for (label, value) in fields {
+				buffers.metadata.apply(&label, &value);
+			}
+
+			(markdown, true)
+		}
+
+		None => (buffers.input.as_str(), false),
+	};
+
+	let math = args.math.unwrap_or(false);
+	let extracted;
+	let mut math_spans = Vec::new();
+	let markdown = if math {
+		(extracted, math_spans) = math::extract(markdown);
+		extracted.as_str()
+	} else {
+		markdown
+	};
+
 	let mut options = Options::empty();
 	options.insert(Options::ENABLE_TABLES);
-	let parser = Parser::new_ext(&buffers.input, options);
+	options.insert(Options::ENABLE_STRIKETHROUGH);
+	options.insert(Options::ENABLE_TASKLISTS);
+	options.insert(Options::ENABLE_FOOTNOTES);
+	if args.smart_punctuation.unwrap_or(false) {
+		options.insert(Options::ENABLE_SMART_PUNCTUATION);
+	}
+	let events = Parser::new_ext(markdown, options).collect::<Vec<_>>();
+
+	//Front matter takes over metadata entirely, comments are only scanned without it
+	if !has_front_matter {
+		for event in &events {
+			if let Event::Html(html) = event {
+				let html = html.trim();
+				if html.starts_with("<!--") && html.ends_with("-->") {
+					//We are reasonably confident that this is an HTML comment
+
+					let contents = &html["<!--".len()..];
+					let contents = &contents[..contents.len() - "-->".len()];
+
+					if let Some(colon_index) = contents.find(':') {
+						let label = &contents[..colon_index];
+						let trailing = contents[colon_index + 1..].trim();
+						buffers.metadata.apply(label, trailing);
+					}
+				}
+			}
+		}
+	}
+
+	let spoilers = spoiler_contents(&events);
+	let mut events = expand_spoilers(events, &spoilers, options);
+	wrap_figures(&mut events);
+
+	//Known before rendering so rewritten links already point at the slug
+	let url_name = if buffers.metadata.slug.is_empty() {
+		url_name.to_string()
+	} else if is_valid_slug(&buffers.metadata.slug) {
+		buffers.metadata.slug.clone()
+	} else {
+		let path = path.to_path_buf();
+		let slug = buffers.metadata.slug.clone();
+		return Err(BuildError::InvalidSlug { path, slug });
+	};
+	let url_name = url_name.as_str();
+
+	let mut in_code_block = false;
+	let mut word_count = 0;
+
+	//How many elements are open, a `<!--more-->` only cuts the post between blocks
+	let mut depth = 0;
+
+	let mut heading_events: Option<Vec<Event>> = None;
+	let mut heading_text = String::new();
+	let mut used_slugs = HashSet::new();
+
+	//Level, slug, and inner HTML of every heading for the table of contents
+	let mut headings = Vec::new();
+	let mut has_toc = false;
+	let mut has_more = false;
+
+	let rewrite_links = args.rewrite_links.unwrap_or(false);
+	let flat_html = args.flat_html.unwrap_or(false);
+
+	let heading_links = args.heading_links.unwrap_or(false);
+	let page_url = post_url(args, url_name);
+
+	//Fenced languages rendered as a classed div rather than a code block, flags win over built ins
+	let mut callouts = HashMap::new();
+	callouts.insert("image_description", "ImageDescription");
+	for (kind, class) in &args.callouts {
+		callouts.insert(kind.as_str(), class.as_str());
+	}
+
+	//Contents of a `deflist` fenced block, rendered as a whole once it closes
+	let mut deflist: Option<String> = None;
+
+	//Destination, title and alt text of the image being held back, if any
+	let lazy_images = args.lazy_images.unwrap_or(false);
+	let mut image: Option<(CowStr, CowStr, String)> = None;
+
+	//Image and internal link destinations as written, before any rewriting
+	let mut images = Vec::new();
+	let mut links = Vec::new();
+
+	//Plain text of the first paragraph, a stand in for a missing description
+	let auto_description = args.auto_description.unwrap_or(false);
+	let mut first_paragraph = String::new();
+	let mut in_paragraph = false;
+	let mut first_paragraph_done = false;
+
+	//Kept for the whole post as an element dropped in one event may only close in a later one
+	let sanitize_html = args.sanitize_html.unwrap_or(false);
+	let emoji = args.emoji.unwrap_or(false);
+	let mut sanitizer = Sanitizer::default();
+
+	let parser = events.into_iter().filter_map(|event| {
+		//Cleaned before anything else so only our own generated markup gets through unchecked
+		let event = match event {
+			Event::Html(html) if sanitize_html => Event::Html(sanitizer.clean(&html).into()),
+
+			Event::Start(Tag::Link(kind, destination, title))
+				if sanitize_html && !sanitize::is_safe_url(&destination) =>
+			{
+				Event::Start(Tag::Link(kind, CowStr::Borrowed(""), title))
+			}
+
+			Event::Start(Tag::Image(kind, destination, title))
+				if sanitize_html && !sanitize::is_safe_url(&destination) =>
+			{
+				Event::Start(Tag::Image(kind, CowStr::Borrowed(""), title))
+			}
+
+			event => event,
+		};
+
+		match &event {
+			Event::Start(_) => depth += 1,
+			Event::End(_) => depth -= 1,
+			_ => {}
+		}
+
+		match &event {
+			Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+			Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+			Event::Text(text) if !in_code_block => word_count += text.split_whitespace().count(),
+			_ => {}
+		}
+
+		//Code spans arrive as `Event::Code` so only code blocks need to be kept out
+		let event = match event {
+			Event::Text(text) if emoji && !in_code_block && text.contains(':') => {
+				Event::Text(emoji::expand(&text).into_owned().into())
+			}
+			event => event,
+		};
+
+		//Gathered before any rewriting which might swallow text, such as image alt text
+		if let (Event::Text(text) | Event::Code(text), Some(_)) = (&event, &heading_events) {
+			heading_text.push_str(text);
+		}
+
+		if auto_description && !first_paragraph_done {
+			match &event {
+				Event::Start(Tag::Paragraph) => in_paragraph = true,
+				Event::End(Tag::Paragraph) => {
+					in_paragraph = false;
+					first_paragraph_done = !first_paragraph.trim().is_empty();
+				}
+				Event::Text(text) | Event::Code(text) if in_paragraph => {
+					first_paragraph.push_str(text)
+				}
+				Event::SoftBreak | Event::HardBreak if in_paragraph => first_paragraph.push(' '),
+				_ => {}
+			}
+		}
+
+		match &event {
+			Event::Start(Tag::Image(_, destination, _)) => images.push(destination.to_string()),
+			Event::Start(Tag::Link(_, destination, _)) if is_internal_link(destination) => {
+				links.push(destination.to_string())
+			}
+			_ => {}
+		}
+
+		let event = match event {
+			Event::Start(Tag::Link(kind, destination, title)) if rewrite_links || flat_html => {
+				let destination = rewrite_relative_link(args, url_name, destination);
+				Event::Start(Tag::Link(kind, destination, title))
+			}
+
+			Event::Start(Tag::Image(kind, destination, title)) if rewrite_links || flat_html => {
+				let destination = rewrite_relative_link(args, url_name, destination);
+				Event::Start(Tag::Image(kind, destination, title))
+			}
+
+			event => event,
+		};
+
+		//Images are written by hand to carry extra attributes, so their alt text is collected
+		let event = match event {
+			Event::Start(Tag::Image(_, destination, title)) if lazy_images => {
+				image = Some((destination, title, String::new()));
+				return None;
+			}
+
+			Event::End(Tag::Image(..)) if image.is_some() => {
+				let (destination, title, alt) = image.take()?;
+				Event::Html(format_image(&destination, &title, &alt).into())
+			}
+
+			event => {
+				if let Some((_, _, alt)) = &mut image {
+					if let Event::Text(text) | Event::Code(text) = &event {
+						alt.push_str(text);
+					}
+					return None;
+				}
+
+				event
+			}
+		};
+
+		//Heading contents are held back until the end so the id can be derived from their text
+		if let Event::Start(Tag::Heading(_)) = &event {
+			heading_events = Some(Vec::new());
+			return None;
+		}
+
+		if let Event::End(Tag::Heading(level)) = &event {
+			let events = heading_events.take().unwrap_or_default();
+			let text = math::restore_text(&heading_text, &math_spans);
+			let slug = heading_slug(&text, &mut used_slugs);
+			heading_text.clear();
+
+			let mut inner = String::new();
+			html::push_html(&mut inner, events.into_iter());
+			headings.push((*level, slug.clone(), text));
+
+			//The full permalink is left for the page's own script to copy
+			let mut clipboard = String::new();
+			if heading_links && (*level == 2 || *level == 3) {
+				clipboard = format!(r#" data-clipboard="{}#{}""#, escape_html(&page_url), slug);
+			}
 
-	buffers.title.clear();
-	buffers.description.clear();
-	buffers.author.clear();
-	buffers.date.clear();
+			let heading = format!(
+				concat!(
+					r#"<h{level} id="{slug}">{inner}"#,
+					r##"<a href="#{slug}" class="heading-anchor"{clipboard}>#</a></h{level}>"##,
+					"\n",
+				),
+				level = level,
+				slug = slug,
+				inner = inner,
+				clipboard = clipboard,
+			);
+			return Some(Event::Html(heading.into()));
+		}
 
-	let mut additional_feeds = Vec::new();
+		if let Some(events) = &mut heading_events {
+			events.push(event);
+			return None;
+		}
 
-	let parser = parser.map(|event| {
 		if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(language))) = &event {
-			if *language == CowStr::Borrowed("image_description") {
-				return Event::Html(CowStr::Borrowed(r#"<div class="ImageDescription"><p>"#));
+			if language.as_ref() == "deflist" {
+				deflist = Some(String::new());
+				return None;
+			}
+
+			if math && language.as_ref() == "math" {
+				let html = r#"<div class="math display">"#;
+				return Some(Event::Html(CowStr::Borrowed(html)));
+			}
+
+			if let Some(class) = callouts.get(language.as_ref()) {
+				let html = format!(r#"<div class="{}"><p>"#, class);
+				return Some(Event::Html(html.into()));
 			}
 		}
 
 		if let Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(language))) = &event {
-			if *language == CowStr::Borrowed("image_description") {
-				return Event::Html(CowStr::Borrowed(r#"</p></div>"#));
+			if language.as_ref() == "deflist" {
+				let text = deflist.take()?;
+				return Some(Event::Html(format_deflist(&text).into()));
+			}
+
+			if math && language.as_ref() == "math" {
+				return Some(Event::Html(CowStr::Borrowed("</div>\n")));
+			}
+
+			if callouts.contains_key(language.as_ref()) {
+				return Some(Event::Html(CowStr::Borrowed(r#"</p></div>"#)));
 			}
 		}
 
+		if let (Event::Text(text), Some(deflist)) = (&event, &mut deflist) {
+			deflist.push_str(text);
+			return None;
+		}
+
 		if let Event::Html(html) = &event {
-			let html = html.trim();
-			if html.starts_with("<!--") && html.ends_with("-->") {
-				//We are reasonably confident that this is an HTML comment
-
-				let contents = &html["<!--".len()..];
-				let contents = &contents[..contents.len() - "-->".len()];
-
-				if let Some(colon_index) = contents.find(':') {
-					let label = &contents[..colon_index];
-					let trailing = contents[colon_index + 1..].trim();
-
-					match label {
-						"title" => {
-							buffers.title.clear();
-							buffers.title.push_str(trailing);
-						}
-
-						"description" => {
-							buffers.description.clear();
-							buffers.description.push_str(trailing);
-						}
-
-						"author" => {
-							buffers.author.clear();
-							buffers.author.push_str(trailing);
-						}
-
-						"date" => {
-							buffers.date.clear();
-							buffers.date.push_str(trailing);
-						}
-
-						"additional-feed" => {
-							let feed_id = feed_tracker.identify(trailing);
-							additional_feeds.push(feed_id);
-						}
-
-						_ => {}
-					}
+			match html.trim() {
+				TOC_MARKER => has_toc = true,
+				MORE_MARKER if depth == 0 && !has_more => {
+					has_more = true;
+					return Some(Event::Html(CowStr::Borrowed(MORE_ANCHOR)));
 				}
+				_ => {}
 			}
 		}
 
-		event
+		Some(event)
 	});
 
 	buffers.html.clear();
 	html::push_html(&mut buffers.html, parser);
 
-	let blog_entry = build_blog_entry(buffers, path, url_name, additional_feeds);
+	//Second pass, only now are all the headings known
+	if has_toc {
+		let toc = format_toc(&headings);
+		buffers.html = buffers.html.replacen(TOC_MARKER, &toc, 1);
+	}
+
+	if !math_spans.is_empty() {
+		buffers.html = math::restore(&buffers.html, &math_spans);
+	}
+
+	let mut excerpt = None;
+	if has_more {
+		if let Some(index) = buffers.html.find(MORE_ANCHOR) {
+			excerpt = Some(rewrite_excerpt_links(
+				args,
+				url_name,
+				&buffers.html[..index],
+			));
+		}
+	}
+
+	if buffers.metadata.author.is_empty() {
+		if let Some(default_author) = &args.default_author {
+			buffers.metadata.author.push_str(default_author);
+		}
+	}
+
+	if auto_description && buffers.metadata.description.is_empty() {
+		let first_paragraph = math::restore_text(&first_paragraph, &math_spans);
+		buffers.metadata.description = derive_description(&first_paragraph);
+	}
+
+	let body = std::mem::take(&mut buffers.html);
+	let mut blog_entry = build_blog_entry(
+		args,
+		now,
+		&buffers.metadata,
+		path,
+		url_name,
+		word_count,
+		body,
+	)?;
+	blog_entry.excerpt = excerpt;
+	blog_entry.images = images;
+	blog_entry.links = links;
 
 	buffers.output.clear();
 	buffers.output.push_str("<!DOCTYPE html>\n");
@@ -272,17 +984,33 @@ fn process_markdown(
 		"\n<head>"
 		r#"<meta charset="UTF-8">"#
 	));
-	if !buffers.title.is_empty() {
-		let _ = writeln!(buffers.output, "<title>{}</title>", buffers.title);
+	let _ = writeln!(
+		buffers.output,
+		r#"<meta name="generator" content="floc_blog {}">"#,
+		VERSION
+	);
+	if !buffers.metadata.title.is_empty() {
+		let _ = writeln!(buffers.output, "<title>{}</title>", buffers.metadata.title);
 	}
-	if let Some(favicon) = &args.favicon {
+	push_favicon(args, &mut buffers.output);
+	let page_url = if buffers.metadata.canonical.is_empty() {
+		post_url(args, url_name)
+	} else {
+		buffers.metadata.canonical.clone()
+	};
+	if !args.no_canonical.unwrap_or(false) {
 		let _ = writeln!(
 			buffers.output,
-			r#"<link rel="shortcut icon" type="image/png" href="{}" />"#,
-			favicon
+			r#"<link rel="canonical" href="{}" />"#,
+			escape_html(&page_url)
 		);
 	}
-	if !buffers.description.is_empty() {
+	let _ = writeln!(
+		buffers.output,
+		r#"<meta property="og:url" content="{}" />"#,
+		escape_html(&page_url)
+	);
+	if !buffers.metadata.description.is_empty() {
 		let _ = write!(
 			buffers.output,
 			multiline!(
@@ -290,22 +1018,72 @@ fn process_markdown(
 				r#"<meta property="og:title" content="{title}" />"#
 				r#"<meta property="og:description" content="{description}" />"#
 			),
-			title = buffers.title,
-			description = buffers.description,
+			title = buffers.metadata.title,
+			description = buffers.metadata.description,
 		);
 	}
-	if let Some(favicon_url) = &args.favicon {
+	let _ = write!(
+		buffers.output,
+		multiline!(
+			r#"<meta property="og:type" content="article" />"#
+			r#"<meta property="article:published_time" content="{}" />"#
+		),
+		blog_entry.date.to_rfc3339(),
+	);
+	if let Some(author) = &blog_entry.author {
+		let _ = writeln!(
+			buffers.output,
+			r#"<meta property="article:author" content="{}" />"#,
+			escape_html(author)
+		);
+	}
+	let social_image = blog_entry
+		.image
+		.as_deref()
+		.or(args.og_default_image.as_deref());
+	if let Some(image) = social_image {
 		let _ = writeln!(
 			buffers.output,
 			r#"<meta name="og:image" content="{}">"#,
-			favicon_url,
+			image
+		);
+	}
+	if let Some(card) = &args.twitter_card {
+		let _ = write!(
+			buffers.output,
+			multiline!(
+				r#"<meta name="twitter:card" content="{card}" />"#
+				r#"<meta name="twitter:title" content="{title}" />"#
+				r#"<meta name="twitter:description" content="{description}" />"#
+			),
+			card = card,
+			title = escape_html(&blog_entry.title),
+			description = escape_html(&blog_entry.description),
 		);
 	}
-	if !buffers.author.is_empty() {
+	//A post's own image is always offered to Twitter, the site wide default only with a card
+	if blog_entry.image.is_some() || args.twitter_card.is_some() {
+		if let Some(image) = social_image {
+			let _ = writeln!(
+				buffers.output,
+				r#"<meta name="twitter:image" content="{}">"#,
+				image
+			);
+		}
+	}
+	if !buffers.metadata.author.is_empty() {
 		let _ = writeln!(
 			buffers.output,
 			r#"<meta name="author" content="{}" />"#,
-			buffers.author
+			buffers.metadata.author
+		);
+	}
+	//Only controls crawling, listing is left to `unlisted` and `draft`
+	if !buffers.metadata.robots.is_empty() {
+		let _ = writeln!(
+			buffers.output,
+			r#"<meta name="robots" content="{}" />"#,
+			escape_html(&buffers.metadata.robots)
 		);
 	}
 	if let Some(opengraph_locale) = &args.opengraph_locale {
@@ -323,423 +1101,2687 @@ fn process_markdown(
 		);
 	}
 
-	if !fragments.css.is_empty() {
-		buffers.output.push_str("<style>\n");
-		buffers.output.push_str(&fragments.css);
-		buffers.output.push_str("</style>\n");
+	if args.structured_data.unwrap_or(false) {
+		let structured_data = format_structured_data(args, &blog_entry);
+		buffers.output.push_str(&structured_data);
 	}
 
-	buffers.output.push_str("</head>\n\n");
+	if math {
+		buffers.output.push_str(KATEX_HEAD);
+	}
 
-	if !fragments.header.is_empty() {
-		let format_str = date_format_string(blog_entry.date.date());
-		let formatted_date = format!("{}", blog_entry.date.format(format_str));
+	push_stylesheet(args, fragments, &mut buffers.output);
+
+	//Closed only once every post is known, translations are linked from in here
+	blog_entry.head = std::mem::take(&mut buffers.output);
+
+	let header_fragment = fragments.for_template(&buffers.metadata.template, "header");
+	let footer_fragment = fragments.for_template(&buffers.metadata.template, "footer");
+
+	if !header_fragment.is_empty() {
+		let formatted_date = format_pretty_date(args, &blog_entry.date);
+		let formatted_updated = format_updated_date(args, &blog_entry);
+		let word_count = blog_entry.word_count.to_string();
+		let reading_time = format_reading_time(blog_entry.reading_minutes);
+		let url = post_url(args, &blog_entry.url_name);
+		let home = blog_list_page_url(args, 1);
+
+		let template_values = HeaderValues {
+			title: &blog_entry.title,
+			description: &blog_entry.description,
+			date: &formatted_date,
+			updated: &formatted_updated,
+			draft: blog_entry.draft,
+			word_count: &word_count,
+			reading_time: &reading_time,
+			url: &url,
+			slug: &blog_entry.url_name,
+			author: blog_entry.author.as_deref().unwrap_or(""),
+			home: &home,
+		};
 
-		let template_values = map![
-			"TITLE" => blog_entry.title.as_str(),
-			"DESCRIPTION" => blog_entry.description.as_str(),
-			"DATE" => formatted_date.as_str(),
-		];
-
-		let header = format_template(fragments.header.clone(), template_values);
+		let header = format_template(header_fragment.to_string(), template_values.map())?;
 		buffers.output.push_str(&header);
 		buffers.output.push_str("\n\n");
 	}
 
-	buffers.output.push_str(&buffers.html);
+	blog_entry.header = std::mem::take(&mut buffers.output);
+	blog_entry.footer = footer_fragment.to_string();
 
-	if !fragments.footer.is_empty() {
-		buffers.output.push_str("\n\n");
-		buffers.output.push_str(&fragments.footer);
+	Ok(blog_entry)
+}
+
+//Everything a header fragment can refer to, posts and the 404 page alike
+struct HeaderValues<'a> {
+	title: &'a str,
+	description: &'a str,
+	date: &'a str,
+	updated: &'a str,
+	draft: bool,
+	word_count: &'a str,
+	reading_time: &'a str,
+	url: &'a str,
+	slug: &'a str,
+	author: &'a str,
+	home: &'a str,
+}
+
+impl<'a> HeaderValues<'a> {
+	fn map(&self) -> HashMap<&'static str, &'a str> {
+		map![
+			"TITLE" => self.title,
+			"DESCRIPTION" => self.description,
+			"DATE" => self.date,
+			"UPDATED" => self.updated,
+			"DRAFT" => if self.draft { "true" } else { "false" },
+			"WORDCOUNT" => self.word_count,
+			"READINGTIME" => self.reading_time,
+			"URL" => self.url,
+			"SLUG" => self.slug,
+			"AUTHOR" => self.author,
+			"HOME" => self.home,
+		]
+	}
+}
+
+fn format_not_found_page(
+	args: &Arguments,
+	not_found: &str,
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<String, BuildError> {
+	const TITLE: &str = "Page Not Found";
+
+	let home = blog_list_page_url(args, 1);
+	let template_values = HeaderValues {
+		title: TITLE,
+		description: "",
+		date: "",
+		updated: "",
+		draft: false,
+		word_count: "0",
+		reading_time: "",
+		url: &home,
+		slug: "404",
+		author: "",
+		home: &home,
+	};
+
+	let mut output = String::new();
+	output.push_str("<!DOCTYPE html>\n");
+	if let Some(language) = &args.language {
+		let _ = writeln!(output, r#"<html lang="{}">"#, language);
+	}
+	output.push_str(multiline!(
+		"\n<head>"
+		r#"<meta charset="UTF-8">"#
+	));
+	let _ = writeln!(
+		output,
+		r#"<meta name="generator" content="floc_blog {}">"#,
+		VERSION
+	);
+	let _ = writeln!(output, "<title>{}</title>", TITLE);
+	push_favicon(args, &mut output);
+	output.push_str(feed_links);
+	push_stylesheet(args, fragments, &mut output);
+	output.push_str("</head>\n\n");
+
+	if !fragments.header.is_empty() {
+		let header = format_template(fragments.header.clone(), template_values.map())?;
+		output.push_str(&header);
+		output.push_str("\n\n");
+	}
+
+	output.push_str(&format_template(
+		not_found.to_string(),
+		template_values.map(),
+	)?);
+
+	//There is no post to relate to or come before or after, so all of those are left empty
+	if is_templated_footer(&fragments.footer) {
+		let template_values = map![
+			"RELATED!" => "",
+			"PREV_URL" => "",
+			"PREV_TITLE" => "",
+			"NEXT_URL" => "",
+			"NEXT_TITLE" => "",
+		];
+		output.push_str("\n\n");
+		output.push_str(&format_template(fragments.footer.clone(), template_values)?);
+	} else if !fragments.footer.is_empty() {
+		output.push_str("\n\n");
+		output.push_str(&fragments.footer);
 	}
 
-	blog_entry
+	Ok(output)
 }
 
-//I honestly can't be bothered right now, it's fine
-#[allow(clippy::too_many_arguments)]
-fn process_file(
+fn write_not_found_page(
 	args: &Arguments,
-	feed_tracker: &mut FeedTracker,
-	path: &Path,
-	output_path: PathBuf,
-	url_name: &str,
+	feed_links: &str,
 	fragments: &Fragments,
-	buffers: &mut Buffers,
-	blog_entries: &mut Vec<BlogEntry>,
-) {
-	if let Some(dir_path) = output_path.parent() {
-		/*
-		 * NOTE: Silently swallow failure to create output path.
-		 * If the path does not exist the write will still catch
-		 * the error. Otherwise if this failed for some other
-		 * reason but the write can still succeed then we do not
-		 * care that this failed.
-		 */
-		let _ = std::fs::create_dir_all(dir_path);
+) -> Result<(), BuildError> {
+	let not_found = match &fragments.not_found {
+		Some(not_found) => not_found,
+		None => return Ok(()),
+	};
+
+	let page = format_not_found_page(args, not_found, feed_links, fragments)?;
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push("404.html");
+
+	let page = postprocess_html(args, &page);
+	if let Err(err) = output::write(args, &output_path, page.as_bytes()) {
+		let path = output_path;
+		return Err(BuildError::WriteHtml { path, err });
 	}
 
-	let is_markdown = path.extension().map(|p| p.to_str()) == Some(Some("md"));
+	Ok(())
+}
 
-	if !is_markdown {
-		if let Err(err) = std::fs::copy(&path, &output_path) {
-			eprintln!(
-				"Error copying input file '{}' to '{}': {}",
-				path.to_string_lossy(),
-				output_path.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
-		}
-	} else {
-		let mut file = match File::open(&path) {
-			Ok(file) => file,
+//A paragraph holding nothing but a titled image becomes a figure captioned by the title
+fn wrap_figures(events: &mut [Event]) {
+	let mut index = 0;
+	while index + 1 < events.len() {
+		let caption = match (&events[index], &events[index + 1]) {
+			(Event::Start(Tag::Paragraph), Event::Start(Tag::Image(_, _, title)))
+				if !title.is_empty() =>
+			{
+				escape_html(title)
+			}
 
-			Err(err) => {
-				eprintln!(
-					"Error reading input file '{}': {}",
-					path.to_string_lossy(),
-					err
-				);
-				std::process::exit(-1);
+			_ => {
+				index += 1;
+				continue;
 			}
 		};
 
-		buffers.input.clear();
-		if let Err(err) = file.read_to_string(&mut buffers.input) {
-			eprintln!(
-				"Error reading input markdown file '{}': {}",
-				path.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
-		}
+		let image_end = events[index + 1..]
+			.iter()
+			.position(|event| matches!(event, Event::End(Tag::Image(..))))
+			.map(|offset| index + 1 + offset);
 
-		let blog_entry = process_markdown(args, path, url_name, feed_tracker, fragments, buffers);
-		blog_entries.push(blog_entry);
+		let paragraph_end = match image_end {
+			Some(image_end) => image_end + 1,
+			None => break,
+		};
 
-		if let Err(err) = std::fs::write(&output_path, &buffers.output) {
-			eprintln!(
-				"Error writing HTML to path '{}': {}",
-				output_path.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
+		if let Some(Event::End(Tag::Paragraph)) = events.get(paragraph_end) {
+			events[index] = Event::Html("<figure>".into());
+			let figcaption = format!("<figcaption>{}</figcaption></figure>\n", caption);
+			events[paragraph_end] = Event::Html(figcaption.into());
 		}
+
+		index = paragraph_end;
 	}
 }
 
-fn process_dir(
+fn format_image(destination: &str, title: &str, alt: &str) -> String {
+	let mut image = format!(
+		r#"<img src="{}" alt="{}""#,
+		escape_html(destination),
+		escape_html(alt)
+	);
+	if !title.is_empty() {
+		let _ = write!(image, r#" title="{}""#, escape_html(title));
+	}
+	image.push_str(r#" loading="lazy" decoding="async" />"#);
+	image
+}
+
+fn is_absolute_link(destination: &str) -> bool {
+	const ABSOLUTE_PREFIXES: &[&str] = &["http", "//", "/", "#", "mailto:"];
+	ABSOLUTE_PREFIXES
+		.iter()
+		.any(|prefix| destination.starts_with(prefix))
+}
+
+//Where a post is served, `<url_name>/` normally but `<url_name>.html` with `--flat-html`
+fn post_url(args: &Arguments, url_name: &str) -> String {
+	if args.flat_html.unwrap_or(false) {
+		format!("{}/{}.html", args.blog_base_url, url_name)
+	} else {
+		format!("{}/{}", args.blog_base_url, url_name)
+	}
+}
+
+fn rewrite_relative_link<'a>(
 	args: &Arguments,
-	feed_tracker: &mut FeedTracker,
-	folder_name: &OsStr,
-	dir_path: &Path,
-	fragments: &Fragments,
-	buffers: &mut Buffers,
-	blog_entries: &mut Vec<BlogEntry>,
-) {
-	let url_name = folder_name.to_string_lossy();
-	let dir = match std::fs::read_dir(dir_path) {
-		Ok(dir) => dir,
+	url_name: &str,
+	destination: CowStr<'a>,
+) -> CowStr<'a> {
+	if is_absolute_link(&destination) {
+		return destination;
+	}
 
-		Err(err) => {
-			eprintln!(
-				"Error opening dir '{}': {}",
-				dir_path.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
+	//A flat page sits beside its post folder rather than in it, so must reach inside
+	if !args.rewrite_links.unwrap_or(false) {
+		let folder_name = url_name.rsplit('/').next().unwrap_or(url_name);
+		return CowStr::from(format!("{}/{}", folder_name, destination));
+	}
+
+	//Site relative when hosted under a base path, otherwise fully absolute
+	let rewritten = match &args.base_path {
+		Some(_) => with_base_path(args, &format!("{}/{}", url_name, destination)),
+		None => format!("{}/{}/{}", args.blog_base_url, url_name, destination),
+	};
+	CowStr::from(rewritten)
+}
+
+/*
+ * NOTE: An excerpt is shown on list pages which sit elsewhere than the post,
+ * so links and images relative to the post, jump links included, are made
+ * absolute. The excerpt is already HTML so its attributes are rewritten as
+ * text, raw HTML in the post gets the same treatment as markdown this way.
+ */
+fn rewrite_excerpt_links(args: &Arguments, url_name: &str, excerpt: &str) -> String {
+	const URL_ATTRIBUTES: &[&str] = &["href=\"", "src=\"", "href='", "src='"];
+
+	let page_url = post_url(args, url_name);
+	let page_dir = match url_name.rsplit_once('/') {
+		Some((parent, _)) if args.flat_html.unwrap_or(false) => {
+			format!("{}/{}", args.blog_base_url, parent)
 		}
+		None if args.flat_html.unwrap_or(false) => args.blog_base_url.clone(),
+		_ => page_url.clone(),
 	};
 
-	for entry in dir {
-		match entry {
-			Ok(entry) => {
-				let file_path = entry.path();
-				let file_name = file_path.file_name().unwrap_or_else(|| {
-					eprintln!(
-						"Failed to get filename for '{}'",
-						file_path.to_string_lossy()
-					);
-					std::process::exit(-1);
-				});
-				let extension = file_path
-					.extension()
-					.map(|e| e.to_str())
-					.unwrap_or(Some(""))
-					.unwrap_or("");
-
-				let output_path = {
-					let mut output_path = args.output_dir.clone();
-					output_path.push(folder_name);
-
-					if extension == "md" {
-						if file_name != "content.md" {
-							eprintln!(
-								"Error, markdown file '{}' is not named 'content.md'",
-								file_path.to_string_lossy()
-							);
-							std::process::exit(-1);
-						}
-						output_path.push("index.html");
-					} else {
-						output_path.push(file_name);
-					}
+	let mut rewritten = String::with_capacity(excerpt.len());
+	let mut rest = excerpt;
+	loop {
+		let next = URL_ATTRIBUTES
+			.iter()
+			.filter_map(|attribute| Some((rest.find(attribute)?, attribute)))
+			.min_by_key(|(index, _)| *index);
+		let (index, attribute) = match next {
+			Some(next) => next,
+			None => break,
+		};
 
-					output_path
-				};
+		let value_start = index + attribute.len();
+		rewritten.push_str(&rest[..value_start]);
+		rest = &rest[value_start..];
 
-				process_file(
-					args,
-					feed_tracker,
-					&file_path,
-					output_path,
-					&url_name,
-					fragments,
-					buffers,
-					blog_entries,
-				);
-			}
+		let quote = &attribute[attribute.len() - 1..];
+		let value_end = rest.find(quote).unwrap_or(rest.len());
+		let value = &rest[..value_end];
 
-			Err(err) => {
-				eprintln!(
-					"Error walking dir '{}': {}",
-					dir_path.to_string_lossy(),
-					err
-				);
-				std::process::exit(-1);
-			}
+		let has_scheme = value.split('/').next().unwrap_or("").contains(':');
+		if value.starts_with('#') {
+			rewritten.push_str(&escape_html(&page_url));
+		} else if !has_scheme && !is_absolute_link(value) {
+			rewritten.push_str(&escape_html(&page_dir));
+			rewritten.push('/');
 		}
+		rewritten.push_str(value);
+		rest = &rest[value_end..];
 	}
-}
 
-fn format_rss(args: &Arguments, feed_id: Option<u32>, blog_entries: &[BlogEntry]) -> String {
-	let items = {
-		let mut items = String::new();
+	rewritten.push_str(rest);
+	rewritten
+}
 
-		for entry in blog_entries {
-			if let Some(feed_id) = feed_id {
-				if !entry.additional_feeds.contains(&feed_id) {
-					continue;
-				}
+//Prefixes site local paths (root relative or not) with `--base-path` when given, and not already under it
+fn with_base_path(args: &Arguments, link: &str) -> String {
+	const EXTERNAL_PREFIXES: &[&str] = &["http", "//", "#", "mailto:", "data:"];
+
+	match &args.base_path {
+		Some(base_path)
+			if !EXTERNAL_PREFIXES
+				.iter()
+				.any(|prefix| link.starts_with(prefix)) =>
+		{
+			let base_path = base_path.trim_end_matches('/');
+			let under_base_path = link
+				.strip_prefix(base_path)
+				.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+			if under_base_path && !base_path.is_empty() {
+				return link.to_string();
 			}
 
-			write!(
-				items,
-				multiline!(
-					"<item>"
-					"	<title>{title}</title>"
-					"	<description>{description}</description>"
-					"	<pubDate>{date}</pubDate>"
-					"	<link>{base_url}/{url_name}</link>"
-					"</item>"
-				),
-				title = entry.title,
-				description = entry.description,
-				date = entry.date.to_rfc2822(),
-				base_url = args.blog_base_url,
-				url_name = entry.url_name,
-			)
-			.unwrap();
+			format!("{}/{}", base_path, link.trim_start_matches('/'))
 		}
 
-		items
-	};
+		_ => link.to_string(),
+	}
+}
 
-	let rss = format!(
-		multiline!(
-			r#"<?xml version="1.0"?>"#
-			"<!--RSS generated {date} by floc_blog {version}-->"
-			r#"<rss version="2.0">"#
-			r#"<channel>"#
-			"<language>{language}</language>"
-			"<title>{title}</title>"
-			"<generator>floc_blog {version}</generator>"
-			"\n{items}"
-			r#"</channel>"#
-			r#"</rss>"#
-		),
-		date = Utc::now().to_rfc2822(),
-		version = VERSION,
-		title = args.opengraph_site_name.as_deref().unwrap_or(""),
-		language = args.language.clone().unwrap_or_else(|| "en_US".to_string()),
-		items = items,
-	);
+//Modern browsers look for `icon`, `shortcut icon` is kept for older ones
+fn push_favicon(args: &Arguments, output: &mut String) {
+	if let Some(favicon) = &args.favicon {
+		let _ = write!(
+			output,
+			multiline!(
+				r#"<link rel="icon" href="{favicon}" />"#
+				r#"<link rel="shortcut icon" type="image/png" href="{favicon}" />"#
+			),
+			favicon = with_base_path(args, favicon),
+		);
+	}
+}
 
-	rss
+//Inline by default so every page stands alone, `--external-css` links the shared file instead
+fn push_stylesheet(args: &Arguments, fragments: &Fragments, output: &mut String) {
+	if fragments.css.is_empty() {
+		return;
+	}
+
+	if args.external_css.unwrap_or(false) {
+		let _ = writeln!(
+			output,
+			r#"<link rel="stylesheet" href="{}">"#,
+			with_base_path(args, &format!("/{}", stylesheet_name(args, fragments)))
+		);
+	} else {
+		output.push_str("<style>\n");
+		output.push_str(&fragments.css);
+		output.push_str("</style>\n");
+	}
 }
 
-fn format_blog_list(
-	args: &Arguments,
-	blog_entries: Vec<BlogEntry>,
-	fragments: Fragments,
-) -> String {
-	let formatted_entries = {
-		let mut formatted_entries = String::new();
+fn stylesheet_css<'a>(args: &Arguments, fragments: &'a Fragments) -> Cow<'a, str> {
+	if args.minify.unwrap_or(false) {
+		Cow::Owned(minify::minify_css(&fragments.css))
+	} else {
+		Cow::Borrowed(&fragments.css)
+	}
+}
 
-		for entry in blog_entries {
-			let format_str = date_format_string(entry.date.date());
-			let formatted_date = format!("{}", entry.date.format(format_str));
+//With `--hash-assets` the name changes along with the contents so browsers never serve a stale copy
+fn stylesheet_name(args: &Arguments, fragments: &Fragments) -> String {
+	if args.hash_assets.unwrap_or(false) {
+		let hash = stable_hash(&stylesheet_css(args, fragments)) as u32;
+		format!("style.{:08x}.css", hash)
+	} else {
+		String::from("style.css")
+	}
+}
 
-			let link = format!("{}/{}", args.blog_base_url, entry.url_name);
+fn write_stylesheet(args: &Arguments, fragments: &Fragments) -> Result<(), BuildError> {
+	if fragments.css.is_empty() {
+		return Ok(());
+	}
 
-			let template_values = map![
-				"TITLE" => entry.title.as_str(),
-				"DESCRIPTION" => entry.description.as_str(),
-				"DATE" => formatted_date.as_str(),
-				"LINK" => link.as_str(),
-			];
+	let css = stylesheet_css(args, fragments);
 
-			let formatted = format_template(fragments.blog_entry.clone(), template_values);
-			formatted_entries.push_str(&formatted);
-		}
+	let mut output_path = args.output_dir.clone();
+	output_path.push(stylesheet_name(args, fragments));
 
-		formatted_entries
+	if let Err(err) = output::write(args, &output_path, css.as_bytes()) {
+		let path = output_path;
+		return Err(BuildError::WriteStylesheet { path, err });
+	}
+
+	Ok(())
+}
+
+//Final pass over a whole HTML page right before it is written, feeds never go through here
+fn postprocess_html<'a>(args: &Arguments, html: &'a str) -> Cow<'a, str> {
+	if args.minify.unwrap_or(false) {
+		Cow::Owned(minify::minify_html(html))
+	} else if args.pretty.unwrap_or(false) {
+		Cow::Owned(pretty::pretty_html(html))
+	} else {
+		Cow::Borrowed(html)
+	}
+}
+
+fn post_page_path(args: &Arguments, url_name: &str) -> PathBuf {
+	if args.flat_html.unwrap_or(false) {
+		let (parent, name) = url_name.rsplit_once('/').unwrap_or(("", url_name));
+		post_output_path(args, parent, OsStr::new(&format!("{}.html", name)))
+	} else {
+		post_output_path(args, url_name, OsStr::new("index.html"))
+	}
+}
+
+fn post_output_path(args: &Arguments, url_name: &str, file_name: &OsStr) -> PathBuf {
+	let mut output_path = args.output_dir.clone();
+	output_path.extend(url_name.split('/').filter(|part| !part.is_empty()));
+
+	/*
+	 * NOTE: Silently swallow failure to create output path.
+	 * If the path does not exist the write will still catch
+	 * the error. Otherwise if this failed for some other
+	 * reason but the write can still succeed then we do not
+	 * care that this failed.
+	 */
+	let _ = output::create_dir_all(args, &output_path);
+
+	output_path.push(file_name);
+	output_path
+}
+
+fn is_markdown(path: &Path) -> bool {
+	path.extension().map(|e| e.to_str()) == Some(Some("md"))
+}
+
+fn copy_post_asset(args: &Arguments, path: &Path, url_name: &str) -> Result<(), BuildError> {
+	let file_name = match path.file_name() {
+		Some(file_name) => file_name,
+		None => {
+			let path = path.to_path_buf();
+			return Err(BuildError::MissingFileName { path });
+		}
 	};
 
-	let template_values = map![
-		"ENTRIES" => formatted_entries.as_str(),
-	];
-	format_template(fragments.blog_list, template_values)
+	let output_path = post_output_path(args, url_name, file_name);
+	if let Err(err) = output::copy(args, path, &output_path) {
+		let from = path.to_path_buf();
+		let to = output_path;
+		return Err(BuildError::CopyFile { from, to, err });
+	}
+
+	Ok(())
 }
 
-fn process_rss_feed(
+fn process_file(
 	args: &Arguments,
-	feed_name: &str,
-	feed_id: Option<u32>,
-	blog_entries: &[BlogEntry],
-) {
-	let rss = format_rss(args, feed_id, blog_entries);
+	now: DateTime<Utc>,
+	path: &Path,
+	url_name: &str,
+	fragments: &Fragments,
+	cache: &Cache,
+	buffers: &mut Buffers,
+) -> Result<BlogEntry, BuildError> {
+	let modified = cache::modified(path);
 
-	let mut output_path = args.output_dir.clone();
-	output_path.push(format!("{}.rss", feed_name));
+	let mut file = match File::open(path) {
+		Ok(file) => file,
 
-	if let Err(err) = std::fs::write(&output_path, &rss) {
-		eprintln!(
-			"Error writing RSS feed file'{}': {}",
-			output_path.to_string_lossy(),
-			err
+		Err(err) => {
+			let path = path.to_path_buf();
+			return Err(BuildError::ReadFile { path, err });
+		}
+	};
+
+	buffers.input.clear();
+	if let Err(err) = file.read_to_string(&mut buffers.input) {
+		let path = path.to_path_buf();
+		return Err(BuildError::ReadMarkdown { path, err });
+	}
+
+	if let Some(blog_entry) = cache.reuse(path, modified, &buffers.input) {
+		verbose!(
+			args,
+			"Reusing cached render of '{}'",
+			path.to_string_lossy()
 		);
-		std::process::exit(-1);
+		return Ok(blog_entry);
 	}
-}
 
-fn date_format_string<T: Datelike>(date: T) -> &'static str {
-	match date.day() {
-		1 | 21 | 31 => "%A the %est of %B %Y",
-		2 | 22 | 32 => "%A the %end of %B %Y",
-		3 | 23 | 33 => "%A the %erd of %B %Y",
-		_ => "%A the %eth of %B %Y",
+	let blog_entry = process_markdown(args, now, path, url_name, fragments, buffers)?;
+	if is_published(args, now, &blog_entry) {
+		cache.record(path, modified, &buffers.input, &blog_entry);
 	}
+	Ok(blog_entry)
 }
 
-fn main() {
-	let args = arguments::parse();
+/*
+ * NOTE: Every directory below an input dir is collected, with the url name
+ * being its path relative to the input dir. Those holding a markdown file
+ * become posts while the rest act as categories, any other files they hold
+ * are copied over as is. Parents come before their children so walk order
+ * stays deterministic.
+ *
+ * Files directly in an input dir are collected on their own, a markdown file
+ * becomes a post named after its file stem and anything else is copied into
+ * the output root.
+ */
+fn collect_dirs(
+	dir: std::fs::ReadDir,
+	parent_url_name: Option<&str>,
+	post_dirs: &mut Vec<(String, PathBuf)>,
+) -> Result<(), BuildError> {
+	for entry in dir {
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(err) => return Err(BuildError::WalkInputDir { err }),
+		};
+		let path = entry.path();
 
-	let fragments = Fragments::retrieve_or_shim(args.fragments_dir.clone());
+		let is_dir = entry.file_type().map(|e| e.is_dir()).unwrap_or(false);
+		let is_root = parent_url_name.is_none();
 
-	let input_dir = match std::fs::read_dir(&args.input_dir) {
-		Ok(input_dir) => input_dir,
+		let file_name = path.file_stem().map(|name| name.to_str());
+		if (is_dir || is_root) && file_name == Some(Some("index")) {
+			return Err(BuildError::IndexNamedFile { path });
+		}
 
-		Err(err) => {
-			eprintln!(
-				"Error opening input dir '{}': {}",
-				args.input_dir.to_string_lossy(),
-				err
-			);
-			std::process::exit(-1);
+		if !is_dir {
+			if is_root {
+				let url_name = match path.file_stem() {
+					Some(stem) if is_markdown(&path) => stem.to_string_lossy().into_owned(),
+					_ => String::new(),
+				};
+				post_dirs.push((url_name, path));
+			}
+
+			//Otherwise handled by `process_dir` along with the rest of its directory
+			continue;
 		}
-	};
 
-	/*
-	 * NOTE: Silently swallow error here because it can fail
-	 * if the folder does not already exist which is fine.
-	 * If there really is something wrong with the path or
-	 * permissions or whatever then the actual outputting will
-	 * catch that. Otherwise we are uninterested in failure
-	 * here.
-	 */
-	let _ = std::fs::remove_dir_all(&args.output_dir);
+		let folder_name = path
+			.file_name()
+			.expect("Somehow failed to get folder filename")
+			.to_string_lossy();
+		let url_name = match parent_url_name {
+			Some(parent_url_name) => format!("{}/{}", parent_url_name, folder_name),
+			None => folder_name.into_owned(),
+		};
 
-	let mut blog_entries = Vec::new();
-	let mut feed_tracker = FeedTracker::new();
+		let subdir = match std::fs::read_dir(&path) {
+			Ok(subdir) => subdir,
+			Err(err) => return Err(BuildError::OpenDir { path, err }),
+		};
+
+		post_dirs.push((url_name.clone(), path));
+		collect_dirs(subdir, Some(&url_name), post_dirs)?;
+	}
+
+	Ok(())
+}
+
+//Copies the whole tree verbatim, noting every file so clobbering can be reported later
+fn copy_assets(
+	args: &Arguments,
+	dir_path: &Path,
+	output_path: PathBuf,
+	copied: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), BuildError> {
+	let dir = match std::fs::read_dir(dir_path) {
+		Ok(dir) => dir,
 
-	let mut buffers = Buffers {
-		input: String::new(),
-		html: String::new(),
-		output: String::new(),
-		title: String::new(),
-		description: String::new(),
-		author: String::new(),
-		date: String::new(),
+		Err(err) => {
+			let path = dir_path.to_path_buf();
+			return Err(BuildError::OpenDir { path, err });
+		}
 	};
 
-	for entry in input_dir {
-		match entry {
-			Ok(entry) => {
-				let path = entry.path();
-
-				let file_name = path.file_stem().map(|name| name.to_str());
-				if let Some(Some("index")) = file_name {
-					eprintln!(
-						"Error, file '{}' should not be named 'index.*'",
-						path.to_string_lossy(),
-					);
-					std::process::exit(-1);
-				}
+	//NOTE: Swallowed for the same reason as in `post_output_path`
+	let _ = output::create_dir_all(args, &output_path);
 
-				let is_dir = entry.file_type().map(|e| e.is_dir()).unwrap_or(false);
-
-				if is_dir {
-					let folder_name = path
-						.file_name()
-						.expect("Somehow failed to get folder filename");
-
-					process_dir(
-						&args,
-						&mut feed_tracker,
-						folder_name,
-						&path,
-						&fragments,
-						&mut buffers,
-						&mut blog_entries,
-					);
-				} else {
-					eprintln!(
-						"Found file '{}' at root level in input directory",
-						path.to_string_lossy()
-					);
-					std::process::exit(-1);
-				}
-			}
+	for entry in dir {
+		let entry = match entry {
+			Ok(entry) => entry,
 
 			Err(err) => {
-				eprintln!("Error walking input dir: {}", err);
-				std::process::exit(-1);
+				let path = dir_path.to_path_buf();
+				return Err(BuildError::WalkDir { path, err });
+			}
+		};
+
+		let from = entry.path();
+		let to = output_path.join(entry.file_name());
+
+		if entry.file_type().map(|e| e.is_dir()).unwrap_or(false) {
+			copy_assets(args, &from, to, copied)?;
+		} else {
+			if let Err(err) = output::copy(args, &from, &to) {
+				return Err(BuildError::CopyFile { from, to, err });
 			}
+			copied.push((from, to));
 		}
 	}
 
-	blog_entries.sort_by(|left, right| right.date.cmp(&left.date));
+	Ok(())
+}
 
-	process_rss_feed(&args, "feed", None, &blog_entries);
-	for (feed_name, feed_id) in feed_tracker.ids {
-		process_rss_feed(&args, &feed_name, Some(feed_id), &blog_entries);
+/*
+ * NOTE: Site root references may be satisfied by the output or any input or
+ * assets dir, while those relative to a post may be satisfied by its source
+ * or output folder. Both sides are checked so a dry run validates too.
+ */
+fn asset_exists(args: &Arguments, reference: &str, post: Option<&BlogEntry>) -> bool {
+	const REMOTE_PREFIXES: &[&str] = &["http://", "https://", "//", "data:"];
+	if REMOTE_PREFIXES
+		.iter()
+		.any(|prefix| reference.starts_with(prefix))
+	{
+		return true;
 	}
 
-	{
-		let list_page = format_blog_list(&args, blog_entries, fragments);
+	let reference = reference.split(['?', '#']).next().unwrap_or(reference);
+	if reference.is_empty() {
+		return true;
+	}
 
-		let mut output_path = args.output_dir;
-		output_path.push("index.html");
+	let mut candidates = Vec::new();
+	match (reference.strip_prefix('/'), post) {
+		(Some(_), _) | (None, None) => {
+			let mut reference = reference;
+			if let Some(base_path) = &args.base_path {
+				let base_path = base_path.trim_end_matches('/');
+				reference = reference.strip_prefix(base_path).unwrap_or(reference);
+			}
+			let reference = reference.trim_start_matches('/');
+
+			candidates.push(args.output_dir.join(reference));
+			candidates.extend(args.input_dirs.iter().map(|dir| dir.join(reference)));
+			if let Some(assets_dir) = &args.assets_dir {
+				candidates.push(assets_dir.join(reference));
+			}
+		}
+
+		(None, Some(post)) => {
+			candidates.push(post.source_dir.join(reference));
+
+			let mut output_path = args.output_dir.clone();
+			output_path.extend(post.url_name.split('/'));
+			candidates.push(output_path.join(reference));
+		}
+	}
+
+	candidates.iter().any(|candidate| candidate.exists())
+}
+
+fn warn_missing_assets(args: &Arguments, blog_entries: &[BlogEntry]) {
+	let site_images = [
+		("--favicon", &args.favicon),
+		("--og-default-image", &args.og_default_image),
+	];
+	for (flag, image) in site_images {
+		if let Some(image) = image {
+			if !asset_exists(args, image, None) {
+				warning!(args, "Warning, {} '{}' does not exist", flag, image);
+			}
+		}
+	}
+
+	for entry in blog_entries {
+		for image in entry.image.iter().chain(&entry.images) {
+			if !asset_exists(args, image, Some(entry)) {
+				warning!(
+					args,
+					"Warning, post '{}' references missing asset '{}'",
+					entry.url_name,
+					image
+				);
+			}
+		}
+	}
+}
+
+fn is_internal_link(destination: &str) -> bool {
+	const EXTERNAL_PREFIXES: &[&str] = &["http", "//", "mailto:"];
+	!EXTERNAL_PREFIXES
+		.iter()
+		.any(|prefix| destination.starts_with(prefix))
+}
+
+//Resolves `..` by hand as the target may never have been written to disk
+fn normalize_path(path: &Path) -> PathBuf {
+	let mut normalized = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normalized.pop();
+			}
+			component => normalized.push(component),
+		}
+	}
+	normalized
+}
+
+/*
+ * NOTE: A link may name the produced file itself, the folder of a post or
+ * list page, or leave off the `.html` of a flat page. Relative links are
+ * resolved from the post's output folder, just as `--rewrite-links` does.
+ */
+fn link_resolves(
+	args: &Arguments,
+	link: &str,
+	entry: &BlogEntry,
+	produced: &HashSet<PathBuf>,
+) -> bool {
+	let link = link.split(['?', '#']).next().unwrap_or(link);
+	if link.is_empty() {
+		return true;
+	}
+
+	let mut target = args.output_dir.clone();
+	match link.strip_prefix('/') {
+		Some(_) => {
+			let mut link = link;
+			if let Some(base_path) = &args.base_path {
+				let base_path = base_path.trim_end_matches('/');
+				link = link.strip_prefix(base_path).unwrap_or(link);
+			}
+			target.extend(link.split('/').filter(|part| !part.is_empty()));
+		}
+
+		None => {
+			target.extend(entry.url_name.split('/'));
+			target.extend(link.split('/').filter(|part| !part.is_empty()));
+		}
+	}
+	let target = normalize_path(&target);
+
+	let mut flat_page = target.clone().into_os_string();
+	flat_page.push(".html");
+
+	let candidates = [target.join("index.html"), PathBuf::from(flat_page), target];
+	candidates
+		.iter()
+		.any(|candidate| produced.contains(candidate))
+}
+
+fn warn_broken_links(args: &Arguments, blog_entries: &[&BlogEntry]) {
+	let produced = output::take_produced();
+	let produced = produced
+		.iter()
+		.map(|path| normalize_path(path))
+		.collect::<HashSet<_>>();
+
+	for entry in blog_entries {
+		for link in &entry.links {
+			if !link_resolves(args, link, entry, &produced) {
+				warning!(
+					args,
+					"Warning, post '{}' links to '{}' which this build did not produce",
+					entry.url_name,
+					link
+				);
+			}
+		}
+	}
+}
+
+//Generated output wins over a shared asset at the same path, but never silently
+fn warn_clobbered_assets(args: &Arguments, copied: &[(PathBuf, PathBuf)]) {
+	for (from, to) in copied {
+		let unchanged = match (std::fs::read(from), std::fs::read(to)) {
+			(Ok(original), Ok(output)) => original == output,
+			_ => true,
+		};
+
+		if !unchanged {
+			warning!(
+				args,
+				"Warning, asset '{}' was overwritten by generated output at '{}'",
+				from.to_string_lossy(),
+				to.to_string_lossy()
+			);
+		}
+	}
+}
+
+//Leeway for filesystems with coarse modification times
+const STALE_MARGIN: Duration = Duration::from_secs(2);
+
+fn warn_stale_outputs(args: &Arguments, dir_path: &Path, build_start: SystemTime) {
+	let dir = match std::fs::read_dir(dir_path) {
+		Ok(dir) => dir,
+		Err(_) => return,
+	};
+
+	for entry in dir.flatten() {
+		let path = entry.path();
+
+		if entry.file_type().map(|e| e.is_dir()).unwrap_or(false) {
+			warn_stale_outputs(args, &path, build_start);
+			continue;
+		}
+
+		let modified = entry.metadata().and_then(|metadata| metadata.modified());
+		if let Ok(modified) = modified {
+			if modified < build_start {
+				warning!(
+					args,
+					"Warning, stale file '{}' was not produced by this build",
+					path.to_string_lossy()
+				);
+			}
+		}
+	}
+}
+
+//Posts sharing a date fall back to their url name so every list and feed orders them the same
+fn newest_first(l: &BlogEntry, r: &BlogEntry) -> std::cmp::Ordering {
+	r.date
+		.cmp(&l.date)
+		.then_with(|| l.url_name.cmp(&r.url_name))
+}
+
+//Future dated posts are held back entirely with `--skip-future`, until a later rebuild
+//Checked against one `now` per build so a post can't be scheduled in one place and published in another
+fn is_scheduled(args: &Arguments, now: DateTime<Utc>, entry: &BlogEntry) -> bool {
+	args.skip_future.unwrap_or(false) && entry.date > now
+}
+
+fn is_published(args: &Arguments, now: DateTime<Utc>, entry: &BlogEntry) -> bool {
+	(!entry.draft || args.include_drafts.unwrap_or(false)) && !is_scheduled(args, now, entry)
+}
+
+const RELATED_POSTS: usize = 3;
+
+//Other listed posts sharing the most tags, ties go to the newer post
+fn related_posts<'a>(entry: &BlogEntry, listed: &[&'a BlogEntry]) -> Vec<&'a BlogEntry> {
+	let tags = entry
+		.tags
+		.iter()
+		.map(|tag| slugify(tag))
+		.collect::<HashSet<_>>();
+
+	let mut related = listed
+		.iter()
+		.filter(|other| other.url_name != entry.url_name)
+		.map(|other| {
+			let shared = other.tags.iter().filter(|tag| tags.contains(&slugify(tag)));
+			(shared.count(), *other)
+		})
+		.filter(|(shared, _)| *shared > 0)
+		.collect::<Vec<_>>();
+
+	//Stable so entries sharing as many tags keep their newest first order
+	related.sort_by_key(|(shared, _)| std::cmp::Reverse(*shared));
+	related
+		.into_iter()
+		.take(RELATED_POSTS)
+		.map(|(_, other)| other)
+		.collect()
+}
+
+/*
+ * NOTE: Variants which were not built are skipped with a warning rather than
+ * linked to. A post missing from its own list is linked in the `--language`,
+ * with a warning when there is none, and the first variant listed is offered
+ * as the `x-default`.
+ */
+fn push_translations(
+	args: &Arguments,
+	entry: &BlogEntry,
+	written: &HashSet<&str>,
+	page: &mut String,
+) {
+	if entry.translations.is_empty() {
+		return;
+	}
+
+	let mut translations = entry.translations.clone();
+	let listed_self = translations
+		.iter()
+		.any(|(_, url_name)| *url_name == entry.url_name);
+	match (listed_self, &args.language) {
+		(false, Some(language)) => translations.push((language.clone(), entry.url_name.clone())),
+
+		//Without a link back to itself search engines ignore the whole set
+		(false, None) => warning!(
+			args,
+			"Warning, post '{}' has translations but neither lists itself nor has a --language to be linked in",
+			entry.url_name
+		),
+
+		(true, _) => {}
+	}
+
+	let mut first = None;
+	for (language, url_name) in &translations {
+		if !written.contains(url_name.as_str()) {
+			warning!(
+				args,
+				"Warning, post '{}' lists translation '{}' which was not built",
+				entry.url_name,
+				url_name
+			);
+			continue;
+		}
+
+		let url = post_url(args, url_name);
+		let _ = writeln!(
+			page,
+			r#"<link rel="alternate" hreflang="{}" href="{}" />"#,
+			language, url
+		);
+		first.get_or_insert(url);
+	}
+
+	if let Some(url) = first {
+		let _ = writeln!(
+			page,
+			r#"<link rel="alternate" hreflang="x-default" href="{}" />"#,
+			url
+		);
+	}
+}
+
+//Footers were long copied as is so only those referring to one of these are templated
+const FOOTER_KEYS: &[&str] = &[
+	"RELATED",
+	"PREV_URL",
+	"PREV_TITLE",
+	"NEXT_URL",
+	"NEXT_TITLE",
+];
+
+fn is_templated_footer(footer: &str) -> bool {
+	FOOTER_KEYS
+		.iter()
+		.any(|key| footer.contains(&format!("${}", key)))
+}
+
+fn format_post_page(
+	args: &Arguments,
+	entry: &BlogEntry,
+	listed: &[&BlogEntry],
+	written: &HashSet<&str>,
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<String, BuildError> {
+	let mut page = entry.head.clone();
+	push_translations(args, entry, written, &mut page);
+	page.push_str(feed_links);
+	page.push_str("</head>\n\n");
+	page.push_str(&entry.header);
+	page.push_str(&entry.body);
+
+	if is_templated_footer(&entry.footer) {
+		let mut related = String::new();
+		for other in related_posts(entry, listed) {
+			related.push_str(&format_blog_entry(args, other, fragments)?);
+		}
+
+		//Listed newest first so the previous, older, post comes after, unlisted posts have neither
+		let position = listed
+			.iter()
+			.position(|other| other.url_name == entry.url_name);
+		let prev = position.and_then(|position| listed.get(position + 1));
+		let next = position
+			.and_then(|position| position.checked_sub(1))
+			.and_then(|position| listed.get(position));
+
+		let link = |other: Option<&&BlogEntry>| match other {
+			Some(other) => post_url(args, &other.url_name),
+			None => String::new(),
+		};
+		let prev_url = link(prev);
+		let next_url = link(next);
+
+		let template_values = map![
+			"RELATED!" => related.as_str(),
+			"PREV_URL" => prev_url.as_str(),
+			"PREV_TITLE" => prev.map_or("", |prev| prev.title.as_str()),
+			"NEXT_URL" => next_url.as_str(),
+			"NEXT_TITLE" => next.map_or("", |next| next.title.as_str()),
+		];
+
+		page.push_str("\n\n");
+		page.push_str(&format_template(entry.footer.clone(), template_values)?);
+	} else if !entry.footer.is_empty() {
+		page.push_str("\n\n");
+		page.push_str(&entry.footer);
+	}
+
+	Ok(page)
+}
+
+/*
+ * Post pages are only written once every post has been processed as their
+ * footers can refer to other posts. `blog_entries` must be sorted newest first.
+ */
+fn write_post_pages(
+	args: &Arguments,
+	blog_entries: &[BlogEntry],
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<(), BuildError> {
+	let listed = blog_entries
+		.iter()
+		.filter(|entry| !entry.unlisted)
+		.collect::<Vec<_>>();
+	let written = blog_entries
+		.iter()
+		.map(|entry| entry.url_name.as_str())
+		.collect::<HashSet<_>>();
+
+	blog_entries.par_iter().try_for_each(|entry| {
+		let page = format_post_page(args, entry, &listed, &written, feed_links, fragments)?;
+
+		let output_path = post_page_path(args, &entry.url_name);
+
+		let html = postprocess_html(args, &page);
+		if let Err(err) = output::write(args, &output_path, html.as_bytes()) {
+			let path = output_path;
+			return Err(BuildError::WriteHtml { path, err });
+		}
+
+		Ok(())
+	})
+}
+
+//The post a folder holds, if any, and every other file which gets copied alongside it
+struct ProcessedDir {
+	entry: Option<BlogEntry>,
+	assets: Vec<PathBuf>,
+}
+
+//Self contained so post folders can be processed in parallel
+fn process_dir(
+	args: &Arguments,
+	now: DateTime<Utc>,
+	url_name: &str,
+	dir_path: &Path,
+	fragments: &Fragments,
+	cache: &Cache,
+) -> Result<ProcessedDir, BuildError> {
+	let mut buffers = Buffers::new();
+
+	//A lone file straight in an input dir
+	if dir_path.is_file() {
+		if !is_markdown(dir_path) {
+			let assets = vec![dir_path.to_path_buf()];
+			return Ok(ProcessedDir {
+				entry: None,
+				assets,
+			});
+		}
+
+		verbose!(args, "Processing '{}'", dir_path.to_string_lossy());
+		let entry = process_file(
+			args,
+			now,
+			dir_path,
+			url_name,
+			fragments,
+			cache,
+			&mut buffers,
+		)?;
+		let assets = Vec::new();
+		return Ok(ProcessedDir {
+			entry: Some(entry),
+			assets,
+		});
+	}
+
+	let dir = match std::fs::read_dir(dir_path) {
+		Ok(dir) => dir,
+
+		Err(err) => {
+			let path = dir_path.to_path_buf();
+			return Err(BuildError::OpenDir { path, err });
+		}
+	};
+
+	let mut file_paths = Vec::new();
+	for entry in dir {
+		match entry {
+			Ok(entry) => {
+				//Subdirectories were collected separately and get processed on their own
+				if entry.file_type().map(|e| e.is_dir()).unwrap_or(false) {
+					continue;
+				}
+
+				file_paths.push(entry.path());
+			}
+
+			Err(err) => {
+				let path = dir_path.to_path_buf();
+				return Err(BuildError::WalkDir { path, err });
+			}
+		}
+	}
+
+	/*
+	 * NOTE: The post is whichever single `.md` file the folder holds, no matter
+	 * its name. Nothing is copied yet as its slug decides where the rest of the
+	 * folder ends up, sorting by name beforehand keeps any error message stable.
+	 */
+	file_paths.sort();
+	let (markdown, assets): (Vec<_>, Vec<_>) =
+		file_paths.into_iter().partition(|path| is_markdown(path));
+
+	if markdown.len() > 1 {
+		return Err(BuildError::MultipleMarkdown {
+			first: markdown[0].clone(),
+			second: markdown[1].clone(),
+		});
+	}
+
+	let mut entry = None;
+	if let Some(markdown) = markdown.first() {
+		verbose!(args, "Processing '{}'", markdown.to_string_lossy());
+		entry = Some(process_file(
+			args,
+			now,
+			markdown,
+			url_name,
+			fragments,
+			cache,
+			&mut buffers,
+		)?);
+	}
+
+	Ok(ProcessedDir { entry, assets })
+}
+
+//Entries belonging to the feed, newest first and capped with `--feed-limit`
+//An additional feed with its own `--feed-config` limit ignores the global `--feed-limit`
+fn feed_entries<'a>(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &'a [BlogEntry],
+) -> impl Iterator<Item = &'a BlogEntry> {
+	let mut configs = args.feed_configs.iter();
+	let configured = feed_id
+		.and_then(|_| configs.find(|(name, _)| name == feed_name))
+		.map(|(_, limit)| *limit);
+	let limit = configured.or(args.feed_limit).unwrap_or(usize::MAX);
+
+	blog_entries
+		.iter()
+		.filter(move |entry| match feed_id {
+			Some(feed_id) => entry.additional_feeds.contains(&feed_id),
+			None => true,
+		})
+		.take(limit)
+}
+
+fn format_rss(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> String {
+	let full_content = args.full_content_feed.unwrap_or(false);
+	let mut last_build = None;
+
+	let items = {
+		let mut items = String::new();
+
+		for entry in feed_entries(args, feed_name, feed_id, blog_entries) {
+			last_build = last_build.max(Some(entry.date));
+
+			let mut creator = String::new();
+			if let Some(author) = &entry.author {
+				let _ = writeln!(
+					creator,
+					"\t<dc:creator>{}</dc:creator>",
+					escape_html(author)
+				);
+			}
+
+			let mut categories = String::new();
+			for tag in &entry.tags {
+				let _ = writeln!(categories, "\t<category>{}</category>", escape_html(tag));
+			}
+
+			let mut content = String::new();
+			if full_content {
+				let body = cdata(&entry.body);
+				let _ = writeln!(content, "\t<content:encoded>{}</content:encoded>", body);
+			}
+
+			write!(
+				items,
+				multiline!(
+					"<item>"
+					"	<title>{title}</title>"
+					"	<description>{description}</description>"
+					"	<pubDate>{date}</pubDate>"
+					"	<link>{link}</link>"
+					r#"	<guid isPermaLink="false">{guid:016x}</guid>"#
+					"{categories}{creator}{content}</item>"
+				),
+				title = escape_html(&entry.title),
+				description = escape_html(&entry.summary),
+				date = entry.date.to_rfc2822(),
+				link = escape_html(&post_url(args, &entry.url_name)),
+				guid = stable_hash(&entry.url_name),
+				categories = categories,
+				creator = creator,
+				content = content,
+			)
+			.unwrap();
+		}
+
+		items
+	};
+
+	let mut ttl = String::new();
+	if let Some(minutes) = args.feed_ttl {
+		let _ = writeln!(ttl, "<ttl>{}</ttl>", minutes);
+	}
+
+	let rss = format!(
+		multiline!(
+			r#"<?xml version="1.0"?>"#
+			"<!--RSS generated {date} by floc_blog {version}-->"
+			r#"<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:atom="http://www.w3.org/2005/Atom">"#
+			r#"<channel>"#
+			"<language>{language}</language>"
+			"<title>{title}</title>"
+			"<link>{base_url}</link>"
+			r#"<atom:link href="{base_url}/{feed_name}.rss" rel="self" type="application/rss+xml" />"#
+			"<generator>floc_blog {version}</generator>"
+			"<lastBuildDate>{last_build}</lastBuildDate>"
+			"{ttl}\n{items}"
+			r#"</channel>"#
+			r#"</rss>"#
+		),
+		date = generated_date(args).to_rfc2822(),
+		version = VERSION,
+		title = escape_html(args.opengraph_site_name.as_deref().unwrap_or("")),
+		language = escape_html(args.language.as_deref().unwrap_or("en_US")),
+		base_url = escape_html(&args.blog_base_url),
+		feed_name = escape_html(feed_name),
+		last_build = last_build
+			.unwrap_or_else(|| generated_date(args))
+			.to_rfc2822(),
+		ttl = ttl,
+		items = items,
+	);
+
+	rss
+}
+
+fn format_atom(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> String {
+	let full_content = args.full_content_feed.unwrap_or(false);
+	let mut updated = None;
+	let mut missing_author = false;
+
+	let entries = {
+		let mut entries = String::new();
+
+		for entry in feed_entries(args, feed_name, feed_id, blog_entries) {
+			updated = updated.max(Some(last_modified(entry)));
+
+			let mut content = String::new();
+			match &entry.author {
+				Some(author) => {
+					let author = escape_html(author);
+					let _ = writeln!(content, "	<author><name>{}</name></author>", author);
+				}
+				None => missing_author = true,
+			}
+			if full_content {
+				let body = cdata(&entry.body);
+				let _ = writeln!(content, r#"	<content type="html">{}</content>"#, body);
+			}
+
+			write!(
+				entries,
+				multiline!(
+					"<entry>"
+					"	<title>{title}</title>"
+					"	<summary>{description}</summary>"
+					"	<updated>{date}</updated>"
+					"	<id>{base_url}/{url_name}</id>"
+					r#"	<link rel="alternate" href="{link}"/>"#
+					"{content}</entry>"
+				),
+				title = escape_html(&entry.title),
+				description = escape_html(&entry.summary),
+				date = last_modified(entry).to_rfc3339(),
+				base_url = escape_html(&args.blog_base_url),
+				url_name = escape_html(&entry.url_name),
+				link = escape_html(&post_url(args, &entry.url_name)),
+				content = content,
+			)
+			.unwrap();
+		}
+
+		entries
+	};
+
+	//Atom requires every entry to have an author, one on the feed covers those without
+	let mut author = String::new();
+	if missing_author {
+		let name = args
+			.opengraph_site_name
+			.as_deref()
+			.unwrap_or(&args.blog_base_url);
+		let _ = writeln!(
+			author,
+			"<author><name>{}</name></author>",
+			escape_html(name)
+		);
+	}
+
+	let atom = format!(
+		multiline!(
+			r#"<?xml version="1.0" encoding="utf-8"?>"#
+			"<!--Atom generated {date} by floc_blog {version}-->"
+			r#"<feed xmlns="http://www.w3.org/2005/Atom">"#
+			"<title>{title}</title>"
+			"<id>{base_url}/{feed_name}.atom</id>"
+			r#"<link rel="self" href="{base_url}/{feed_name}.atom"/>"#
+			r#"<link rel="alternate" href="{base_url}"/>"#
+			"<updated>{updated}</updated>"
+			r#"{author}<generator version="{version}">floc_blog</generator>"#
+			"\n{entries}"
+			r#"</feed>"#
+		),
+		author = author,
+		date = generated_date(args).to_rfc2822(),
+		version = VERSION,
+		title = escape_html(args.opengraph_site_name.as_deref().unwrap_or("")),
+		base_url = escape_html(&args.blog_base_url),
+		feed_name = escape_html(feed_name),
+		updated = updated.unwrap_or_else(|| generated_date(args)).to_rfc3339(),
+		entries = entries,
+	);
+
+	atom
+}
+
+//Pinned by `--source-date` or `SOURCE_DATE_EPOCH` so output can be reproduced byte for byte
+fn generated_date(args: &Arguments) -> DateTime<Utc> {
+	if let Some(source_date) = args.source_date {
+		return source_date;
+	}
+
+	match std::env::var("SOURCE_DATE_EPOCH") {
+		Ok(epoch) => match arguments::parse_epoch(&epoch) {
+			Some(date) => date,
+			None => Utc::now(),
+		},
+
+		Err(_) => Utc::now(),
+	}
+}
+
+//FNV-1a, unlike `DefaultHasher` the output is guaranteed to never change between builds
+fn stable_hash(text: &str) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for byte in text.bytes() {
+		hash ^= byte as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	hash
+}
+
+//Wraps text in CDATA, splitting any `]]>` the text itself contains
+fn cdata(text: &str) -> String {
+	format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+fn format_structured_data(args: &Arguments, entry: &BlogEntry) -> String {
+	//Inside a script element `</` could close the tag early, JSON allows escaping the slash
+	let escape = |text: &str| escape_json(text).replace("</", "<\\/");
+
+	let mut author_field = String::new();
+	if let Some(author) = &entry.author {
+		let _ = write!(
+			author_field,
+			multiline!(
+				","
+				r#"	"author": {{"#
+				r#"		"@type": "Person","#
+				r#"		"name": "{}""#
+				"	}}"
+			),
+			escape(author),
+		);
+		author_field = author_field.trim_end().to_string();
+	}
+
+	format!(
+		multiline!(
+			r#"<script type="application/ld+json">"#
+			"{{"
+			r#"	"@context": "https://schema.org","#
+			r#"	"@type": "BlogPosting","#
+			r#"	"headline": "{title}","#
+			r#"	"description": "{description}","#
+			r#"	"datePublished": "{date}","#
+			r#"	"url": "{link}"{author}"#
+			"}}"
+			"</script>"
+		),
+		title = escape(&entry.title),
+		description = escape(&entry.description),
+		date = entry.date.to_rfc3339(),
+		link = escape(&post_url(args, &entry.url_name)),
+		author = author_field,
+	)
+}
+
+fn escape_json(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => {
+				let _ = write!(escaped, "\\u{:04x}", c as u32);
+			}
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
+
+fn format_json_feed(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> String {
+	let full_content = args.full_content_feed.unwrap_or(false);
+	let mut items = Vec::new();
+
+	for entry in feed_entries(args, feed_name, feed_id, blog_entries) {
+		let mut content = String::new();
+		if full_content {
+			let body = escape_json(&entry.body);
+			let _ = writeln!(content, r#"			"content_html": "{}","#, body);
+		}
+
+		let item = format!(
+			multiline!(
+				"		{{"
+				r#"			"id": "{base_url}/{url_name}","#
+				r#"			"url": "{link}","#
+				r#"			"title": "{title}","#
+				r#"			"content_text": "{description}","#
+				r#"{content}			"date_published": "{date}""#
+				"		}}"
+			),
+			base_url = escape_json(&args.blog_base_url),
+			url_name = escape_json(&entry.url_name),
+			link = escape_json(&post_url(args, &entry.url_name)),
+			title = escape_json(&entry.title),
+			description = escape_json(&entry.summary),
+			date = entry.date.to_rfc3339(),
+			content = content,
+		);
+		items.push(item.trim_end().to_string());
+	}
+
+	format!(
+		multiline!(
+			"{{"
+			r#"	"version": "https://jsonfeed.org/version/1.1","#
+			r#"	"title": "{title}","#
+			r#"	"home_page_url": "{base_url}","#
+			r#"	"feed_url": "{base_url}/{feed_name}.json","#
+			r#"	"items": ["#
+			"{items}"
+			"	]"
+			"}}"
+		),
+		title = escape_json(args.opengraph_site_name.as_deref().unwrap_or("")),
+		base_url = escape_json(&args.blog_base_url),
+		feed_name = escape_json(feed_name),
+		items = items.join(",\n"),
+	)
+}
+
+fn format_blog_entry(
+	args: &Arguments,
+	entry: &BlogEntry,
+	fragments: &Fragments,
+) -> Result<String, BuildError> {
+	let formatted_date = format_pretty_date(args, &entry.date);
+	let formatted_updated = format_updated_date(args, entry);
+
+	let link = post_url(args, &entry.url_name);
+	let reading_time = format_reading_time(entry.reading_minutes);
+	let tags = entry.tags.join(", ");
+
+	//Without a `<!--more-->` cut the excerpt is the plain description
+	let excerpt = match &entry.excerpt {
+		Some(excerpt) => Cow::Borrowed(excerpt.as_str()),
+		None => Cow::Owned(escape_html(&entry.summary)),
+	};
+
+	let template_values = map![
+		"TITLE" => entry.title.as_str(),
+		"DESCRIPTION" => entry.summary.as_str(),
+		"EXCERPT!" => excerpt.as_ref(),
+		"DATE" => formatted_date.as_str(),
+		"UPDATED" => formatted_updated.as_str(),
+		"LINK" => link.as_str(),
+		"DRAFT" => if entry.draft { "true" } else { "false" },
+		"FEATURED" => if entry.featured { "true" } else { "false" },
+		"READINGTIME" => reading_time.as_str(),
+		"AUTHOR" => entry.author.as_deref().unwrap_or(""),
+		"TAGS" => tags.as_str(),
+	];
+
+	format_template(fragments.blog_entry.clone(), template_values)
+}
+
+fn format_blog_list(
+	args: &Arguments,
+	blog_entries: &[&BlogEntry],
+	tag: Option<&str>,
+	prev_page: &str,
+	next_page: &str,
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<String, BuildError> {
+	let formatted_entries = {
+		let mut formatted_entries = String::new();
+
+		for entry in blog_entries {
+			formatted_entries.push_str(&format_blog_entry(args, entry, fragments)?);
+		}
+
+		formatted_entries
+	};
+
+	let template_values = map![
+		"ENTRIES!" => formatted_entries.as_str(),
+		"TAG" => tag.unwrap_or(""),
+		"PREV_PAGE" => prev_page,
+		"NEXT_PAGE" => next_page,
+		"FEEDS!" => feed_links,
+	];
+	format_template(fragments.blog_list.clone(), template_values)
+}
+
+//Page one is the root `index.html`, later pages live under `page/<number>/` or at `page/<number>.html`
+fn blog_list_page_url(args: &Arguments, page: usize) -> String {
+	if page == 1 {
+		format!("{}/", args.blog_base_url)
+	} else if args.flat_html.unwrap_or(false) {
+		format!("{}/page/{}.html", args.blog_base_url, page)
+	} else {
+		format!("{}/page/{}/", args.blog_base_url, page)
+	}
+}
+
+/*
+ * NOTE: Featured posts are pinned ahead of the rest whatever their date
+ * and always make the cut, otherwise the newest posts do. `--list-order asc`
+ * only flips how each of the two groups is shown, never which posts they hold.
+ */
+fn listed_entries<'a>(
+	args: &Arguments,
+	blog_entries: impl Iterator<Item = &'a BlogEntry>,
+) -> Vec<&'a BlogEntry> {
+	let limit = args.list_limit.unwrap_or(usize::MAX);
+	let mut entries = blog_entries.collect::<Vec<_>>();
+	entries.sort_by_key(|entry| !entry.featured);
+	entries.truncate(limit);
+
+	if args.list_order.as_deref() == Some("asc") {
+		let featured = entries.iter().take_while(|entry| entry.featured).count();
+		entries[..featured].reverse();
+		entries[featured..].reverse();
+	}
+	entries
+}
+
+fn write_blog_list_pages(
+	args: &Arguments,
+	blog_entries: &[BlogEntry],
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<usize, BuildError> {
+	let all_entries = listed_entries(args, blog_entries.iter());
+
+	let pages = match args.page_size {
+		Some(page_size) if !all_entries.is_empty() => all_entries.chunks(page_size).collect(),
+		_ => vec![all_entries.as_slice()],
+	};
+
+	for (index, page_entries) in pages.iter().enumerate() {
+		let page = index + 1;
+
+		let mut prev_page = String::new();
+		if page > 1 {
+			prev_page = blog_list_page_url(args, page - 1);
+		}
+
+		let mut next_page = String::new();
+		if page < pages.len() {
+			next_page = blog_list_page_url(args, page + 1);
+		}
+
+		let list_page = format_blog_list(
+			args,
+			page_entries,
+			None,
+			&prev_page,
+			&next_page,
+			feed_links,
+			fragments,
+		)?;
+
+		let output_path = if page == 1 {
+			args.output_dir.join("index.html")
+		} else {
+			post_page_path(args, &format!("page/{}", page))
+		};
+
+		let list_page = postprocess_html(args, &list_page);
+		if let Err(err) = output::write(args, &output_path, list_page.as_bytes()) {
+			let path = output_path;
+			return Err(BuildError::WriteBlogList { path, err });
+		}
+	}
+
+	Ok(pages.len())
+}
+
+//Relies on the entries already being sorted newest first
+fn format_archive(
+	args: &Arguments,
+	blog_entries: &[BlogEntry],
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<String, BuildError> {
+	let formatted_entries = {
+		let mut formatted_entries = String::new();
+		let mut current_year = None;
+
+		for entry in blog_entries {
+			let year = entry.date.year();
+			if current_year != Some(year) {
+				current_year = Some(year);
+				let _ = writeln!(formatted_entries, "<h2>{}</h2>", year);
+			}
+
+			formatted_entries.push_str(&format_blog_entry(args, entry, fragments)?);
+		}
+
+		formatted_entries
+	};
+
+	let template_values = map![
+		"ENTRIES!" => formatted_entries.as_str(),
+		"TAG" => "",
+		"PREV_PAGE" => "",
+		"NEXT_PAGE" => "",
+		"FEEDS!" => feed_links,
+	];
+	format_template(fragments.blog_list.clone(), template_values)
+}
+
+fn write_archive(
+	args: &Arguments,
+	blog_entries: &[BlogEntry],
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<(), BuildError> {
+	let archive = format_archive(args, blog_entries, feed_links, fragments)?;
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push("archive");
+
+	//NOTE: Swallowed for the same reason as in `post_output_path`
+	let _ = output::create_dir_all(args, &output_path);
+	output_path.push("index.html");
+
+	let archive = postprocess_html(args, &archive);
+	if let Err(err) = output::write(args, &output_path, archive.as_bytes()) {
+		let path = output_path;
+		return Err(BuildError::WriteArchive { path, err });
+	}
+
+	Ok(())
+}
+
+//Joined straight onto the output dir so it must never be able to step outside of it
+fn is_valid_slug(slug: &str) -> bool {
+	!slug.contains('\\')
+		&& slug
+			.split('/')
+			.all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+//Anything but lowercase ASCII letters, digits and `-` could reach outside of `tags/` or break the URL
+fn slugify(name: &str) -> String {
+	name.trim()
+		.to_lowercase()
+		.chars()
+		.map(|c| match c {
+			'a'..='z' | '0'..='9' | '-' => c,
+			_ => '-',
+		})
+		.collect()
+}
+
+//Anything after `spoiler` in the info string names the block, otherwise it is just a spoiler
+fn spoiler_summary(info: &str) -> Option<&str> {
+	match info.trim().split_once(' ') {
+		Some(("spoiler", summary)) => Some(summary.trim()),
+		None if info.trim() == "spoiler" => Some("Spoiler"),
+		_ => None,
+	}
+}
+
+//The markdown held in each spoiler block, in order, ready to be parsed in its own right
+fn spoiler_contents(events: &[Event]) -> Vec<String> {
+	let mut contents = Vec::new();
+	let mut current: Option<String> = None;
+
+	for event in events {
+		match event {
+			Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+				if spoiler_summary(info).is_some() =>
+			{
+				current = Some(String::new());
+			}
+
+			Event::Text(text) => {
+				if let Some(current) = &mut current {
+					current.push_str(text);
+				}
+			}
+
+			Event::End(Tag::CodeBlock(_)) => {
+				if let Some(current) = current.take() {
+					contents.push(current);
+				}
+			}
+
+			_ => {}
+		}
+	}
+
+	contents
+}
+
+/*
+ * NOTE: The contents of a spoiler are spliced into the post's own events
+ * rather than rendered separately so that they pass through exactly the
+ * same handling as the rest of the post, sanitizing and link rewriting
+ * included.
+ */
+fn expand_spoilers<'a>(
+	events: Vec<Event<'a>>,
+	contents: &'a [String],
+	options: Options,
+) -> Vec<Event<'a>> {
+	let mut expanded = Vec::with_capacity(events.len());
+	let mut contents = contents.iter();
+	let mut in_spoiler = false;
+
+	for event in events {
+		match event {
+			Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+				if spoiler_summary(&info).is_some() =>
+			{
+				let summary = spoiler_summary(&info).unwrap_or_default();
+				let html = format!("<details><summary>{}</summary>\n", escape_html(summary));
+				expanded.push(Event::Html(html.into()));
+
+				if let Some(markdown) = contents.next() {
+					expanded.extend(Parser::new_ext(markdown, options));
+				}
+				in_spoiler = true;
+			}
+
+			Event::End(Tag::CodeBlock(_)) if in_spoiler => {
+				expanded.push(Event::Html(CowStr::Borrowed("</details>\n")));
+				in_spoiler = false;
+			}
+
+			_ if in_spoiler => {}
+
+			event => expanded.push(event),
+		}
+	}
+
+	expanded
+}
+
+/*
+ * NOTE: A term is a line followed by one or more `: definition` lines,
+ * consecutive terms sharing a single list. Anything which does not fit,
+ * such as a definition with no term, becomes a plain paragraph instead.
+ */
+fn format_deflist(text: &str) -> String {
+	let mut html = String::new();
+	let mut in_list = false;
+
+	let lines = text
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
+		.collect::<Vec<_>>();
+
+	let mut index = 0;
+	while index < lines.len() {
+		let term = lines[index];
+		let definitions = lines[index + 1..]
+			.iter()
+			.take_while(|line| line.starts_with(':'))
+			.collect::<Vec<_>>();
+
+		if term.starts_with(':') || definitions.is_empty() {
+			if in_list {
+				html.push_str("</dl>\n");
+				in_list = false;
+			}
+			let _ = writeln!(html, "<p>{}</p>", escape_html(term));
+			index += 1;
+			continue;
+		}
+
+		if !in_list {
+			html.push_str("<dl>\n");
+			in_list = true;
+		}
+		let _ = writeln!(html, "<dt>{}</dt>", escape_html(term));
+		for definition in &definitions {
+			let definition = definition[1..].trim();
+			let _ = writeln!(html, "<dd>{}</dd>", escape_html(definition));
+		}
+		index += 1 + definitions.len();
+	}
+
+	if in_list {
+		html.push_str("</dl>\n");
+	}
+	html
+}
+
+//Nested list of links to every h2 through h4, by plain text as the heading's own links can't nest
+fn format_toc(headings: &[(u32, String, String)]) -> String {
+	let mut toc = String::new();
+	let mut depth = 0;
+
+	for (level, slug, text) in headings {
+		if !(2..=4).contains(level) {
+			continue;
+		}
+
+		let level = level - 1;
+		if level > depth {
+			while depth < level {
+				if depth == 0 {
+					toc.push_str("<ul class=\"TableOfContents\">\n<li>");
+				} else {
+					toc.push_str("\n<ul>\n<li>");
+				}
+				depth += 1;
+			}
+		} else {
+			toc.push_str("</li>\n");
+			while depth > level {
+				toc.push_str("</ul>\n</li>\n");
+				depth -= 1;
+			}
+			toc.push_str("<li>");
+		}
+
+		let _ = write!(toc, r##"<a href="#{}">{}</a>"##, slug, escape_html(text));
+	}
+
+	while depth > 0 {
+		toc.push_str("</li>\n</ul>\n");
+		depth -= 1;
+	}
+
+	toc
+}
+
+//Unique within one post, repeated headings get `-2`, `-3`, ... appended
+fn heading_slug(text: &str, used_slugs: &mut HashSet<String>) -> String {
+	let mut slug = String::new();
+	for c in text.trim().chars() {
+		if c.is_alphanumeric() {
+			slug.extend(c.to_lowercase());
+		} else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+			slug.push('-');
+		}
+	}
+
+	let mut slug = slug.trim_matches('-').to_string();
+	if slug.is_empty() {
+		slug.push_str("section");
+	}
+
+	let mut unique = slug.clone();
+	let mut counter = 2;
+	while used_slugs.contains(&unique) {
+		unique = format!("{}-{}", slug, counter);
+		counter += 1;
+	}
+
+	used_slugs.insert(unique.clone());
+	unique
+}
+
+fn write_tag_pages(
+	args: &Arguments,
+	blog_entries: &[BlogEntry],
+	feed_links: &str,
+	fragments: &Fragments,
+) -> Result<(), BuildError> {
+	//Keyed by slug so tags differing only in case share a page, first spelling wins
+	let mut tags = BTreeMap::new();
+	for entry in blog_entries {
+		for tag in &entry.tags {
+			tags.entry(slugify(tag)).or_insert_with(|| tag.clone());
+		}
+	}
+
+	for (slug, tag) in tags {
+		let tagged_entries = blog_entries
+			.iter()
+			.filter(|entry| entry.tags.iter().any(|other| slugify(other) == slug));
+		let tagged_entries = listed_entries(args, tagged_entries);
+
+		let list_page = format_blog_list(
+			args,
+			&tagged_entries,
+			Some(&tag),
+			"",
+			"",
+			feed_links,
+			fragments,
+		)?;
+
+		let mut output_path = args.output_dir.clone();
+		output_path.push("tags");
+		output_path.push(&slug);
+
+		//NOTE: Swallowed for the same reason as in `post_output_path`
+		let _ = output::create_dir_all(args, &output_path);
+		output_path.push("index.html");
+
+		let list_page = postprocess_html(args, &list_page);
+		if let Err(err) = output::write(args, &output_path, list_page.as_bytes()) {
+			let path = output_path;
+			return Err(BuildError::WriteTagList { path, err });
+		}
+	}
+
+	Ok(())
+}
+
+/*
+ * NOTE: The main feeds are titled after `--opengraph-site-name`, when given,
+ * and each additional feed after its own name, one link per enabled format.
+ */
+fn format_feed_links(args: &Arguments, feed_names: &[String]) -> String {
+	let mut formats = vec![("rss", "application/rss+xml")];
+	if args.atom.unwrap_or(false) {
+		formats.push(("atom", "application/atom+xml"));
+	}
+	if args.json_feed.unwrap_or(false) {
+		formats.push(("json", "application/feed+json"));
+	}
+
+	let site_name = args.opengraph_site_name.as_deref().unwrap_or("");
+	let feeds = std::iter::once(("feed", site_name))
+		.chain(feed_names.iter().map(|name| (name.as_str(), name.as_str())));
+
+	let base_url = escape_html(&args.blog_base_url);
+	let mut links = String::new();
+	for (feed_name, title) in feeds {
+		for (extension, mime_type) in &formats {
+			let mut title_attribute = String::new();
+			if !title.is_empty() {
+				title_attribute = format!(r#" title="{}""#, escape_html(title));
+			}
+
+			let _ = writeln!(
+				links,
+				r#"<link rel="alternate" type="{}"{} href="{}/{}.{}" />"#,
+				mime_type,
+				title_attribute,
+				base_url,
+				escape_html(feed_name),
+				extension
+			);
+		}
+	}
+	links
+}
+
+fn process_rss_feed(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> Result<(), BuildError> {
+	let rss = format_rss(args, feed_name, feed_id, blog_entries);
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push(format!("{}.rss", feed_name));
+
+	if let Err(err) = output::write(args, &output_path, &rss) {
+		let path = output_path;
+		return Err(BuildError::WriteFeed {
+			kind: "RSS",
+			path,
+			err,
+		});
+	}
+
+	Ok(())
+}
+
+fn process_atom_feed(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> Result<(), BuildError> {
+	let atom = format_atom(args, feed_name, feed_id, blog_entries);
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push(format!("{}.atom", feed_name));
+
+	if let Err(err) = output::write(args, &output_path, &atom) {
+		let path = output_path;
+		return Err(BuildError::WriteFeed {
+			kind: "Atom",
+			path,
+			err,
+		});
+	}
+
+	Ok(())
+}
 
-		if let Err(err) = std::fs::write(&output_path, &list_page) {
-			eprintln!(
-				"Error writing blog entry list '{}': {}",
-				output_path.to_string_lossy(),
-				err
+fn write_robots(args: &Arguments) -> Result<(), BuildError> {
+	if let Some(assets_dir) = &args.assets_dir {
+		let user_robots = assets_dir.join("robots.txt");
+		if user_robots.is_file() {
+			warning!(
+				args,
+				"Warning, using '{}' from the assets dir instead of generating robots.txt",
+				user_robots.to_string_lossy()
 			);
+			return Ok(());
+		}
+	}
+
+	let mut robots = String::from(multiline!(
+		"User-agent: *"
+		"Allow: /"
+	));
+
+	//Only pointed at when there is a sitemap to find
+	if args.sitemap.unwrap_or(false) {
+		let _ = write!(robots, "\nSitemap: {}/sitemap.xml\n", args.blog_base_url);
+	}
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push("robots.txt");
+
+	if let Err(err) = output::write(args, &output_path, &robots) {
+		let path = output_path;
+		return Err(BuildError::WriteRobots { path, err });
+	}
+
+	Ok(())
+}
+
+fn write_sitemap(args: &Arguments, blog_entries: &[BlogEntry]) -> Result<(), BuildError> {
+	const LASTMOD_FORMAT: &str = "%Y-%m-%d";
+
+	let mut urls = String::new();
+
+	//The home page is always there, it is only as fresh as its newest post if it has any
+	let _ = writeln!(urls, "<url>\n\t<loc>{}/</loc>", args.blog_base_url);
+	if let Some(newest) = blog_entries.iter().map(last_modified).max() {
+		let _ = writeln!(
+			urls,
+			"\t<lastmod>{}</lastmod>",
+			newest.format(LASTMOD_FORMAT)
+		);
+	}
+	urls.push_str("</url>\n");
+
+	for entry in blog_entries {
+		write!(
+			urls,
+			multiline!(
+				"<url>"
+				"	<loc>{link}</loc>"
+				"	<lastmod>{date}</lastmod>"
+				"</url>"
+			),
+			link = post_url(args, &entry.url_name),
+			date = last_modified(entry).format(LASTMOD_FORMAT),
+		)
+		.unwrap();
+	}
+
+	let sitemap = format!(
+		multiline!(
+			r#"<?xml version="1.0" encoding="UTF-8"?>"#
+			r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+			"{urls}"
+			"</urlset>"
+		),
+		urls = urls,
+	);
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push("sitemap.xml");
+
+	if let Err(err) = output::write(args, &output_path, &sitemap) {
+		let path = output_path;
+		return Err(BuildError::WriteSitemap { path, err });
+	}
+
+	Ok(())
+}
+
+//Plain text of rendered HTML for searching, entities decoded and whitespace collapsed
+fn html_to_text(html: &str) -> String {
+	let mut text = String::with_capacity(html.len());
+	let mut rest = html;
+
+	//Script and style contents were never meant to be read, nor the `#` of heading anchors
+	let mut skipping = None;
+
+	while let Some(start) = rest.find('<') {
+		if skipping.is_none() {
+			text.push_str(&rest[..start]);
+		}
+		rest = &rest[start..];
+
+		let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+		let tag = rest[..end].to_lowercase();
+		rest = &rest[end..];
+
+		match skipping {
+			Some(closing) if tag.starts_with(closing) => skipping = None,
+			Some(_) => {}
+			None if tag.starts_with("<script") => skipping = Some("</script"),
+			None if tag.starts_with("<style") => skipping = Some("</style"),
+			None if tag.contains(r#"class="heading-anchor""#) => skipping = Some("</a"),
+			None => text.push(' '),
+		}
+	}
+	if skipping.is_none() {
+		text.push_str(rest);
+	}
+
+	let text = text
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&amp;", "&");
+	text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn write_search_index(args: &Arguments, blog_entries: &[BlogEntry]) -> Result<(), BuildError> {
+	#[derive(Serialize)]
+	struct SearchEntry<'a> {
+		url: String,
+		title: &'a str,
+		description: &'a str,
+		tags: &'a [String],
+		body: String,
+	}
+
+	//Drafts are only ever previews even when `--include-drafts` writes them
+	let entries = blog_entries
+		.iter()
+		.filter(|entry| !entry.draft)
+		.map(|entry| SearchEntry {
+			url: post_url(args, &entry.url_name),
+			title: &entry.title,
+			description: &entry.description,
+			tags: &entry.tags,
+			body: html_to_text(&entry.body),
+		})
+		.collect::<Vec<_>>();
+	let json = serde_json::to_string(&entries).expect("Failed to serialize search index");
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push("search-index.json");
+
+	if let Err(err) = output::write(args, &output_path, &json) {
+		let path = output_path;
+		return Err(BuildError::WriteSearchIndex { path, err });
+	}
+
+	Ok(())
+}
+
+fn process_json_feed(
+	args: &Arguments,
+	feed_name: &str,
+	feed_id: Option<u32>,
+	blog_entries: &[BlogEntry],
+) -> Result<(), BuildError> {
+	let json = format_json_feed(args, feed_name, feed_id, blog_entries);
+
+	let mut output_path = args.output_dir.clone();
+	output_path.push(format!("{}.json", feed_name));
+
+	if let Err(err) = output::write(args, &output_path, &json) {
+		let path = output_path;
+		return Err(BuildError::WriteFeed {
+			kind: "JSON",
+			path,
+			err,
+		});
+	}
+
+	Ok(())
+}
+
+//Rounded up to whole minutes, zero when even one minute would be too long
+fn reading_minutes(args: &Arguments, word_count: usize) -> usize {
+	let speed = args.reading_speed.unwrap_or(DEFAULT_READING_SPEED);
+	if word_count < speed {
+		0
+	} else {
+		word_count.div_ceil(speed)
+	}
+}
+
+fn format_reading_time(minutes: usize) -> String {
+	if minutes == 0 {
+		String::from("less than a minute")
+	} else {
+		format!("{} min", minutes)
+	}
+}
+
+/*
+ * The ordinal suffix (1st, 2nd, 11th, ...) is picked by hand because
+ * chrono has no specifier for it, so it only exists in the default
+ * wording. A `--display-date-format` is handed to chrono untouched.
+ */
+fn format_pretty_date(args: &Arguments, date: &DateTime<Utc>) -> String {
+	if let Some(format_str) = &args.display_date_format {
+		return date.format(format_str).to_string();
+	}
+
+	let format_str = match date.day() {
+		11..=13 => "%A the %eth of %B %Y",
+		day if day % 10 == 1 => "%A the %est of %B %Y",
+		day if day % 10 == 2 => "%A the %end of %B %Y",
+		day if day % 10 == 3 => "%A the %erd of %B %Y",
+		_ => "%A the %eth of %B %Y",
+	};
+
+	date.format(format_str).to_string()
+}
+
+fn format_updated_date(args: &Arguments, entry: &BlogEntry) -> String {
+	match &entry.updated {
+		Some(updated) => format_pretty_date(args, updated),
+		None => String::new(),
+	}
+}
+
+struct BuildSummary {
+	posts: usize,
+	feeds: usize,
+	list_pages: usize,
+	assets: usize,
+	elapsed: Duration,
+}
+
+fn plural(count: usize, noun: &str) -> String {
+	if count == 1 {
+		format!("{} {}", count, noun)
+	} else {
+		format!("{} {}s", count, noun)
+	}
+}
+
+impl std::fmt::Display for BuildSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"Generated {}, {}, {}",
+			plural(self.posts, "post"),
+			plural(self.feeds, "feed"),
+			plural(self.list_pages, "index page")
+		)?;
+
+		if self.assets > 0 {
+			write!(f, " and copied {}", plural(self.assets, "asset"))?;
+		}
+
+		write!(f, " in {}ms", self.elapsed.as_millis())
+	}
+}
+
+fn build(args: &Arguments) -> Result<BuildSummary, BuildError> {
+	let started = Instant::now();
+
+	let fragments = Fragments::retrieve_or_shim(args.fragments_dir.clone())?;
+
+	let mut input_dirs = Vec::with_capacity(args.input_dirs.len());
+	for input_dir_path in &args.input_dirs {
+		match std::fs::read_dir(input_dir_path) {
+			Ok(input_dir) => input_dirs.push(input_dir),
+
+			Err(err) => {
+				let path = input_dir_path.clone();
+				return Err(BuildError::OpenInputDir { path, err });
+			}
+		}
+	}
+
+	let clean = args.clean.unwrap_or(false);
+	if clean {
+		/*
+		 * NOTE: Silently swallow error here because it can fail
+		 * if the folder does not already exist which is fine.
+		 * If there really is something wrong with the path or
+		 * permissions or whatever then the actual outputting will
+		 * catch that. Otherwise we are uninterested in failure
+		 * here.
+		 */
+		let _ = output::remove_dir_all(args, &args.output_dir);
+	}
+
+	let cache = Cache::load(args, &fragments);
+	let now = Utc::now();
+
+	let check_links = args.check_links.unwrap_or(false);
+	if check_links {
+		output::record_produced();
+	}
+
+	//Anything left older than this afterwards was not produced by this build
+	let build_start = SystemTime::now() - STALE_MARGIN;
+
+	let mut copied_assets = Vec::new();
+	if let Some(assets_dir) = &args.assets_dir {
+		copy_assets(
+			args,
+			assets_dir,
+			args.output_dir.clone(),
+			&mut copied_assets,
+		)?;
+	}
+
+	let mut post_dirs = Vec::new();
+	for input_dir in input_dirs {
+		collect_dirs(input_dir, None, &mut post_dirs)?;
+	}
+
+	let processed = post_dirs
+		.par_iter()
+		.map(|(url_name, path)| process_dir(args, now, url_name, path, &fragments, &cache))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	//Post url name to the path it was first seen at, a slug can collide as easily as a folder
+	let mut seen_posts = HashMap::<&str, &Path>::new();
+	for ((_, path), dir) in post_dirs.iter().zip(&processed) {
+		if let Some(entry) = &dir.entry {
+			if let Some(first) = seen_posts.insert(&entry.url_name, path) {
+				return Err(BuildError::DuplicatePost {
+					url_name: entry.url_name.clone(),
+					first: first.to_path_buf(),
+					second: path.clone(),
+				});
+			}
+		}
+	}
+
+	/*
+	 * NOTE: A folder without a post of its own follows its parent wherever
+	 * the parent's slug moved it, so that a post's `images` folder stays next
+	 * to the post, and is left out along with it when the post is. Parents are
+	 * walked before their children so are always resolved first.
+	 */
+	let mut resolved = HashMap::<&str, Option<String>>::new();
+	let mut asset_dirs = Vec::with_capacity(post_dirs.len());
+	for ((url_name, _), dir) in post_dirs.iter().zip(&processed) {
+		let resolved_name = match (&dir.entry, url_name.rsplit_once('/')) {
+			(Some(entry), _) => {
+				Some(entry.url_name.clone()).filter(|_| is_published(args, now, entry))
+			}
+			(None, Some((parent, folder_name))) => match resolved.get(parent) {
+				Some(parent) => parent
+					.as_ref()
+					.map(|parent| format!("{}/{}", parent, folder_name)),
+				None => Some(url_name.clone()),
+			},
+			(None, None) => Some(url_name.clone()),
+		};
+		resolved.insert(url_name, resolved_name.clone());
+		asset_dirs.push(resolved_name);
+	}
+
+	//Only copied once no two posts are known to collide
+	asset_dirs
+		.par_iter()
+		.zip(&processed)
+		.try_for_each(|(url_name, dir)| {
+			if let Some(url_name) = url_name {
+				for asset in &dir.assets {
+					copy_post_asset(args, asset, url_name)?;
+				}
+			}
+			Ok(())
+		})?;
+
+	let processed = processed.into_iter().map(|dir| dir.entry);
+	let mut blog_entries = processed.flatten().collect::<Vec<_>>();
+	let found_posts = blog_entries.len();
+	blog_entries.retain(|entry| is_published(args, now, entry));
+	let written_posts = blog_entries.len();
+
+	//Identified after the parallel pass, in walk order, so feed ids stay deterministic
+	let mut feed_tracker = FeedTracker::new();
+	for entry in blog_entries.iter_mut().filter(|entry| !entry.unlisted) {
+		let feed_names = entry.feed_names.iter();
+		entry.additional_feeds = feed_names.map(|name| feed_tracker.identify(name)).collect();
+	}
+
+	blog_entries.sort_by(newest_first);
+	//Every generated page advertises every feed for browser auto discovery
+	let mut feed_names = feed_tracker.ids.keys().cloned().collect::<Vec<_>>();
+	feed_names.sort();
+	let feed_links = format_feed_links(args, &feed_names);
+
+	write_post_pages(args, &blog_entries, &feed_links, &fragments)?;
+	if args.validate_assets.unwrap_or(false) {
+		warn_missing_assets(args, &blog_entries);
+	}
+
+	//Unlisted posts are still written, they just never get linked to from anywhere
+	let (unlisted_entries, blog_entries): (Vec<_>, Vec<_>) =
+		blog_entries.into_iter().partition(|entry| entry.unlisted);
+
+	verbose!(
+		args,
+		"Publishing {} of {} posts across {} extra feeds",
+		blog_entries.len(),
+		found_posts,
+		feed_tracker.ids.len()
+	);
+
+	let atom = args.atom.unwrap_or(false);
+	let json_feed = args.json_feed.unwrap_or(false);
+
+	//Every feed name gets one file per enabled format
+	let feed_formats = 1 + atom as usize + json_feed as usize;
+	let feeds = (1 + feed_tracker.ids.len()) * feed_formats;
+
+	process_rss_feed(args, "feed", None, &blog_entries)?;
+	if atom {
+		process_atom_feed(args, "feed", None, &blog_entries)?;
+	}
+	if json_feed {
+		process_json_feed(args, "feed", None, &blog_entries)?;
+	}
+	for (feed_name, feed_id) in feed_tracker.ids {
+		process_rss_feed(args, &feed_name, Some(feed_id), &blog_entries)?;
+		if atom {
+			process_atom_feed(args, &feed_name, Some(feed_id), &blog_entries)?;
+		}
+		if json_feed {
+			process_json_feed(args, &feed_name, Some(feed_id), &blog_entries)?;
+		}
+	}
+
+	write_tag_pages(args, &blog_entries, &feed_links, &fragments)?;
+
+	if args.sitemap.unwrap_or(false) {
+		write_sitemap(args, &blog_entries)?;
+	}
+
+	if args.robots.unwrap_or(false) {
+		write_robots(args)?;
+	}
+
+	//Only listed posts are searchable, unlisted posts were already dropped
+	if args.search_index.unwrap_or(false) {
+		write_search_index(args, &blog_entries)?;
+	}
+
+	if args.external_css.unwrap_or(false) {
+		write_stylesheet(args, &fragments)?;
+	}
+
+	if args.archive.unwrap_or(false) {
+		write_archive(args, &blog_entries, &feed_links, &fragments)?;
+	}
+
+	let list_pages = write_blog_list_pages(args, &blog_entries, &feed_links, &fragments)?;
+	write_not_found_page(args, &feed_links, &fragments)?;
+	cache.save(args)?;
+
+	if check_links {
+		let all_entries = blog_entries.iter().chain(&unlisted_entries);
+		warn_broken_links(args, &all_entries.collect::<Vec<_>>());
+	}
+
+	warn_clobbered_assets(args, &copied_assets);
+	if !clean && !args.dry_run.unwrap_or(false) {
+		warn_stale_outputs(args, &args.output_dir, build_start);
+	}
+
+	Ok(BuildSummary {
+		posts: written_posts,
+		feeds,
+		list_pages,
+		assets: copied_assets.len(),
+		elapsed: started.elapsed(),
+	})
+}
+
+const DEFAULT_SERVE_ADDRESS: &str = "localhost:8080";
+
+fn main() {
+	let args = arguments::parse();
+
+	let watch = args.watch.unwrap_or(false);
+
+	if args.minify.unwrap_or(false) && args.pretty.unwrap_or(false) {
+		eprintln!("Error, --minify and --pretty cannot be used together");
+		std::process::exit(-1);
+	}
+
+	let serve = args.serve.unwrap_or(false);
+	let serve_address = args
+		.serve_address
+		.as_deref()
+		.unwrap_or(DEFAULT_SERVE_ADDRESS);
+	if serve && !cfg!(feature = "serve") {
+		eprintln!(
+			"Error, cannot serve on '{}' as floc_blog was built without the 'serve' feature",
+			serve_address
+		);
+		std::process::exit(-1);
+	}
+
+	match build(&args) {
+		Ok(summary) => info!(args, "{}", summary),
+
+		//A broken post while authoring should not stop the watcher from starting
+		Err(err) if watch => eprintln!("{}", err),
+
+		Err(err) => {
+			eprintln!("{}", err);
 			std::process::exit(-1);
 		}
 	}
+
+	#[cfg(feature = "serve")]
+	let server = serve.then(|| serve::spawn(&args, serve_address));
+
+	if watch {
+		watch::watch(&args);
+	}
+
+	#[cfg(feature = "serve")]
+	if let Some(server) = server {
+		let _ = server.join();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn render_figures(markdown: &str) -> String {
+		let mut events = Parser::new(markdown).collect::<Vec<_>>();
+		wrap_figures(&mut events);
+
+		let mut html = String::new();
+		html::push_html(&mut html, events.into_iter());
+		html
+	}
+
+	#[test]
+	fn format_rss_escapes_post_titles() {
+		let args = Arguments::with_required("https://x.test".to_string(), PathBuf::from("out"));
+		let entry = BlogEntry {
+			title: r#"Tom & "Jerry's" <cartoon>"#.to_string(),
+			url_name: "tom-and-jerry".to_string(),
+			date: "2024-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+			..BlogEntry::default()
+		};
+
+		let rss = format_rss(&args, "feed", None, &[entry]);
+		let title = "<title>Tom &amp; &quot;Jerry&#39;s&quot; &lt;cartoon&gt;</title>";
+		assert!(rss.contains(title), "{}", rss);
+	}
+
+	#[test]
+	fn wrap_figures_only_captions_titled_images() {
+		let html = render_figures(concat!(
+			"![A cat](cat.png \"Sleeping cat\")\n",
+			"\n",
+			"![A dog](dog.png)\n",
+		));
+		assert_eq!(
+			html,
+			concat!(
+				r#"<figure><img src="cat.png" alt="A cat" title="Sleeping cat" />"#,
+				"<figcaption>Sleeping cat</figcaption></figure>\n",
+				r#"<p><img src="dog.png" alt="A dog" /></p>"#,
+				"\n",
+			)
+		);
+	}
+
+	#[test]
+	fn newest_first_breaks_date_ties_by_url_name() {
+		let date = "2024-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+		let entry = |url_name: &str, date| BlogEntry {
+			url_name: url_name.to_string(),
+			date,
+			..BlogEntry::default()
+		};
+
+		let older = entry("older", date - chrono::Duration::days(1));
+		let forwards = [entry("a", date), entry("b", date), older.clone()];
+		let backwards = [older, entry("b", date), entry("a", date)];
+
+		for entries in [forwards, backwards] {
+			let mut entries = entries.to_vec();
+			entries.sort_by(newest_first);
+			let order = entries.iter().map(|entry| entry.url_name.as_str());
+			assert_eq!(order.collect::<Vec<_>>(), ["a", "b", "older"]);
+		}
+	}
 }
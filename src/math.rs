@@ -0,0 +1,175 @@
+use crate::template::escape_html;
+
+pub struct Math {
+	display: bool,
+	tex: String,
+}
+
+fn placeholder(index: usize) -> String {
+	//Plain alphanumerics so neither markdown nor smart punctuation will touch it
+	format!("FLOCMATH{}X", index)
+}
+
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+	let trimmed = line.trim_start();
+	let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+	let length = trimmed.chars().take_while(|c| *c == marker).count();
+	if length >= 3 {
+		Some((marker, length))
+	} else {
+		None
+	}
+}
+
+fn extract_from_text(text: &str, output: &mut String, spans: &mut Vec<Math>) {
+	let mut index = 0;
+	while index < text.len() {
+		let rest = &text[index..];
+
+		//Code spans are copied through untouched, `$$` in code is just dollars
+		if rest.starts_with('`') {
+			let ticks = rest.len() - rest.trim_start_matches('`').len();
+			let fence = &rest[..ticks];
+			let end = match rest[ticks..].find(fence) {
+				Some(end) => ticks + end + ticks,
+				None => ticks,
+			};
+
+			output.push_str(&rest[..end]);
+			index += end;
+			continue;
+		}
+
+		let delimiters = if rest.starts_with("$$") {
+			Some(("$$", "$$", true))
+		} else if rest.starts_with("\\(") {
+			Some(("\\(", "\\)", false))
+		} else {
+			None
+		};
+
+		if let Some((open, close, display)) = delimiters {
+			if let Some(end) = rest[open.len()..].find(close) {
+				let tex = rest[open.len()..open.len() + end].trim().to_string();
+				output.push_str(&placeholder(spans.len()));
+				spans.push(Math { display, tex });
+
+				index += open.len() + end + close.len();
+				continue;
+			}
+		}
+
+		//An escaped character must not be mistaken for the start of a delimiter
+		let length = if rest.starts_with('\\') && rest.len() > 1 {
+			1 + rest[1..].chars().next().map_or(0, char::len_utf8)
+		} else {
+			rest.chars().next().map_or(1, char::len_utf8)
+		};
+
+		output.push_str(&rest[..length]);
+		index += length;
+	}
+}
+
+//Indented code can only begin after a blank line, it cannot interrupt a paragraph
+fn is_indented(line: &str) -> bool {
+	line.starts_with("    ") || line.starts_with('\t')
+}
+
+/*
+ * Swaps `$$...$$` display and `\(...\)` inline math for placeholders before
+ * the markdown is parsed, otherwise the parser would eat the backslashes and
+ * smart punctuation would mangle the TeX. Fenced and indented code blocks are
+ * left alone.
+ */
+pub fn extract(markdown: &str) -> (String, Vec<Math>) {
+	let mut output = String::with_capacity(markdown.len());
+	let mut spans = Vec::new();
+
+	let mut fence: Option<(char, usize)> = None;
+	let mut text = String::new();
+
+	let mut in_indented_code = false;
+	let mut previous_blank = true;
+
+	for line in markdown.split_inclusive('\n') {
+		let blank = line.trim().is_empty();
+		let was_blank = std::mem::replace(&mut previous_blank, blank);
+
+		if fence.is_none() && (in_indented_code || was_blank) && is_indented(line) && !blank {
+			extract_from_text(&text, &mut output, &mut spans);
+			text.clear();
+
+			in_indented_code = true;
+			output.push_str(line);
+			continue;
+		}
+
+		//Blank lines within indented code belong to it, anything else ends it
+		if in_indented_code && blank {
+			output.push_str(line);
+			continue;
+		}
+		in_indented_code = false;
+
+		match (fence, fence_marker(line)) {
+			(None, Some(opening)) => {
+				extract_from_text(&text, &mut output, &mut spans);
+				text.clear();
+
+				fence = Some(opening);
+				output.push_str(line);
+			}
+
+			(Some((marker, length)), Some((closing_marker, closing_length)))
+				if marker == closing_marker && closing_length >= length =>
+			{
+				fence = None;
+				output.push_str(line);
+			}
+
+			(Some(_), _) => output.push_str(line),
+
+			(None, None) => text.push_str(line),
+		}
+	}
+
+	extract_from_text(&text, &mut output, &mut spans);
+
+	(output, spans)
+}
+
+//Puts the TeX itself back into plain text, such as a heading's text for its slug
+pub fn restore_text(text: &str, spans: &[Math]) -> String {
+	let mut text = text.to_string();
+
+	for (index, math) in spans.iter().enumerate() {
+		text = text.replacen(&placeholder(index), &math.tex, 1);
+	}
+
+	text
+}
+
+pub fn restore(html: &str, spans: &[Math]) -> String {
+	let mut html = html.to_string();
+
+	for (index, math) in spans.iter().enumerate() {
+		let placeholder = placeholder(index);
+		let tex = escape_html(&math.tex);
+
+		if math.display {
+			//Display math alone in its paragraph takes the paragraph's place
+			let paragraph = format!("<p>{}</p>", placeholder);
+			let block = format!(r#"<div class="math display">{}</div>"#, tex);
+			html = html.replacen(&paragraph, &block, 1);
+
+			let span = format!(r#"<span class="math display">{}</span>"#, tex);
+			html = html.replacen(&placeholder, &span, 1);
+		} else {
+			let span = format!(r#"<span class="math inline">{}</span>"#, tex);
+			html = html.replacen(&placeholder, &span, 1);
+		}
+	}
+
+	html
+}
@@ -0,0 +1,273 @@
+use crate::sanitize::find_tag_end;
+
+//Elements whose contents must come through byte for byte
+pub const RAW_ELEMENTS: &[&str] = &["pre", "code", "textarea", "script", "style"];
+
+//Whitespace next to these can never be visible so it is dropped entirely
+pub const BLOCK_ELEMENTS: &[&str] = &[
+	"!doctype",
+	"html",
+	"head",
+	"body",
+	"meta",
+	"link",
+	"title",
+	"style",
+	"script",
+	"header",
+	"footer",
+	"main",
+	"nav",
+	"section",
+	"article",
+	"div",
+	"p",
+	"h1",
+	"h2",
+	"h3",
+	"h4",
+	"h5",
+	"h6",
+	"ul",
+	"ol",
+	"li",
+	"dl",
+	"dt",
+	"dd",
+	"blockquote",
+	"pre",
+	"hr",
+	"br",
+	"table",
+	"thead",
+	"tbody",
+	"tr",
+	"th",
+	"td",
+	"figure",
+	"figcaption",
+	"details",
+	"summary",
+];
+
+pub fn tag_name(tag: &str) -> String {
+	tag.trim_start_matches('<')
+		.trim_start_matches('/')
+		.chars()
+		.take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+		.collect::<String>()
+		.to_lowercase()
+}
+
+fn is_block(name: Option<&str>) -> bool {
+	match name {
+		Some(name) => BLOCK_ELEMENTS.contains(&name),
+		None => true,
+	}
+}
+
+//Metadata comments (`<!--label: value-->`) are kept, everything else is dropped
+fn is_metadata_comment(comment: &str) -> bool {
+	let contents = &comment["<!--".len()..comment.len() - "-->".len()];
+	match contents.find(':') {
+		Some(colon_index) => {
+			let label = &contents[..colon_index];
+			!label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+		}
+		None => false,
+	}
+}
+
+fn push_collapsed(output: &mut String, text: &str) {
+	let mut in_whitespace = false;
+	for c in text.chars() {
+		//Only ASCII whitespace collapses in HTML, a no-break space must stay as written
+		if c.is_ascii_whitespace() {
+			if !in_whitespace {
+				output.push(' ');
+			}
+			in_whitespace = true;
+		} else {
+			output.push(c);
+			in_whitespace = false;
+		}
+	}
+}
+
+//At-rules whose block holds more rules rather than declarations
+const RULE_BLOCKS: &[&str] = &[
+	"@media",
+	"@supports",
+	"@document",
+	"@layer",
+	"@container",
+	"@scope",
+	"@keyframes",
+	"@-webkit-keyframes",
+];
+
+pub fn minify_css(css: &str) -> String {
+	let mut output = String::with_capacity(css.len());
+
+	let mut chars = css.chars().peekable();
+	let mut pending_space = false;
+
+	//Whether each open block holds declarations, and where the current selector or declaration began
+	let mut blocks: Vec<bool> = Vec::new();
+	let mut statement_start = 0;
+	while let Some(c) = chars.next() {
+		if c == '/' && chars.peek() == Some(&'*') {
+			chars.next();
+			let mut previous = ' ';
+			for c in chars.by_ref() {
+				if previous == '*' && c == '/' {
+					break;
+				}
+				previous = c;
+			}
+			continue;
+		}
+
+		if c == '"' || c == '\'' {
+			if pending_space {
+				output.push(' ');
+				pending_space = false;
+			}
+
+			output.push(c);
+			let mut escaped = false;
+			for inner in chars.by_ref() {
+				output.push(inner);
+				if inner == c && !escaped {
+					break;
+				}
+				escaped = inner == '\\' && !escaped;
+			}
+			continue;
+		}
+
+		if c.is_whitespace() {
+			pending_space = !output.is_empty();
+			continue;
+		}
+
+		//A space before `:` in a selector is a descendant combinator so it has to stay
+		let is_punctuation = match c {
+			'{' | '}' | ';' | ',' | '>' => true,
+			':' => blocks.last() == Some(&true),
+			_ => false,
+		};
+		let follows_punctuation = output.ends_with(['{', '}', ':', ';', ',', '>']);
+		if pending_space && !is_punctuation && !follows_punctuation {
+			output.push(' ');
+		}
+		pending_space = false;
+
+		if c == '}' && output.ends_with(';') {
+			output.pop();
+		}
+		output.push(c);
+
+		match c {
+			'{' => {
+				let prelude = output[statement_start..output.len() - 1].trim();
+				let holds_rules = RULE_BLOCKS.iter().any(|rule| prelude.starts_with(rule));
+				blocks.push(!holds_rules);
+				statement_start = output.len();
+			}
+
+			'}' => {
+				blocks.pop();
+				statement_start = output.len();
+			}
+
+			';' => statement_start = output.len(),
+
+			_ => {}
+		}
+	}
+
+	output
+}
+
+pub fn minify_html(html: &str) -> String {
+	let mut output = String::with_capacity(html.len());
+	let mut previous_tag: Option<String> = None;
+
+	let mut index = 0;
+	while index < html.len() {
+		let rest = &html[index..];
+
+		if rest.starts_with("<!--") {
+			let end = match rest.find("-->") {
+				Some(end) => end + "-->".len(),
+				None => rest.len(),
+			};
+
+			let comment = &rest[..end];
+			if comment.len() >= "<!---->".len()
+				&& comment.ends_with("-->")
+				&& is_metadata_comment(comment)
+			{
+				output.push_str(comment);
+			}
+
+			index += end;
+			continue;
+		}
+
+		if rest.starts_with('<') {
+			let end = find_tag_end(rest)
+				.or_else(|| rest.find('>').map(|end| end + 1))
+				.unwrap_or(rest.len());
+
+			let tag = &rest[..end];
+			output.push_str(tag);
+			index += end;
+
+			let name = tag_name(tag);
+			let is_closing = tag.starts_with("</");
+			if !is_closing && RAW_ELEMENTS.contains(&name.as_str()) {
+				let closing = format!("</{}", name);
+				let rest = &html[index..];
+				let raw_end = rest
+					.as_bytes()
+					.windows(closing.len())
+					.position(|window| window.eq_ignore_ascii_case(closing.as_bytes()))
+					.unwrap_or(rest.len());
+
+				let raw = &rest[..raw_end];
+				if name == "style" {
+					output.push_str(&minify_css(raw));
+				} else {
+					output.push_str(raw);
+				}
+
+				index += raw_end;
+			}
+
+			previous_tag = Some(name);
+			continue;
+		}
+
+		let end = rest.find('<').unwrap_or(rest.len());
+		let text = &rest[..end];
+		index += end;
+
+		if text
+			.trim_matches(|c: char| c.is_ascii_whitespace())
+			.is_empty()
+		{
+			let next_tag = html[index..]
+				.starts_with('<')
+				.then(|| tag_name(&html[index..]));
+			if is_block(previous_tag.as_deref()) || is_block(next_tag.as_deref()) {
+				continue;
+			}
+		}
+
+		push_collapsed(&mut output, text);
+	}
+
+	output
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use brotli::CompressorWriter;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::arguments::Arguments;
+
+/*
+ * Every change to the output directory goes through here so that
+ * `--dry-run` can report what a build would touch instead of doing it.
+ */
+
+fn is_dry_run(args: &Arguments) -> bool {
+	args.dry_run.unwrap_or(false)
+}
+
+//Every path written or copied since `record_produced`, for `--check-links`
+static PRODUCED: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+pub fn record_produced() {
+	*PRODUCED.lock().expect("Output lock was poisoned") = Some(HashSet::new());
+}
+
+pub fn take_produced() -> HashSet<PathBuf> {
+	let mut produced = PRODUCED.lock().expect("Output lock was poisoned");
+	produced.take().unwrap_or_default()
+}
+
+//Recorded even on a dry run, where nothing lands on disk to be checked afterwards
+fn produce(path: &Path) {
+	let mut produced = PRODUCED.lock().expect("Output lock was poisoned");
+	if let Some(produced) = produced.as_mut() {
+		produced.insert(path.to_path_buf());
+	}
+}
+
+fn report(args: &Arguments, path: &Path) {
+	let action = if path.exists() { "overwrite" } else { "create" };
+	info!(args, "Would {} '{}'", action, path.to_string_lossy());
+}
+
+fn write_one(args: &Arguments, path: &Path, contents: &[u8]) -> io::Result<()> {
+	produce(path);
+	write_private(args, path, contents)
+}
+
+//For files kept beside the site rather than in it, never precompressed nor counted as output
+pub fn write_private(args: &Arguments, path: &Path, contents: &[u8]) -> io::Result<()> {
+	if is_dry_run(args) {
+		report(args, path);
+		return Ok(());
+	}
+
+	verbose!(args, "Writing '{}'", path.to_string_lossy());
+	std::fs::write(path, contents)
+}
+
+pub fn write<C: AsRef<[u8]>>(args: &Arguments, path: &Path, contents: C) -> io::Result<()> {
+	write_one(args, path, contents.as_ref())?;
+	precompress(args, path, contents.as_ref())
+}
+
+pub fn copy(args: &Arguments, from: &Path, to: &Path) -> io::Result<()> {
+	produce(to);
+	if is_dry_run(args) {
+		report(args, to);
+	} else {
+		verbose!(
+			args,
+			"Copying '{}' to '{}'",
+			from.to_string_lossy(),
+			to.to_string_lossy()
+		);
+		std::fs::copy(from, to)?;
+	}
+
+	//Only text like assets are worth compressing, images and fonts already are
+	if args.precompress.is_some() && is_compressible(to) {
+		let contents = std::fs::read(from)?;
+		precompress(args, to, &contents)?;
+	}
+
+	Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Precompress {
+	pub gzip: bool,
+	pub brotli: bool,
+}
+
+const DEFAULT_PRECOMPRESS_LEVEL: u32 = 9;
+
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+	"html", "css", "js", "xml", "rss", "atom", "json", "svg", "txt",
+];
+
+//Dotfiles such as the `--incremental` manifest are never served, so never compressed
+fn is_compressible(path: &Path) -> bool {
+	let file_name = path.file_name().map(|name| name.to_string_lossy());
+	if file_name.is_none_or(|name| name.starts_with('.')) {
+		return false;
+	}
+
+	let extension = path.extension().and_then(|extension| extension.to_str());
+	extension.is_some_and(|extension| COMPRESSIBLE_EXTENSIONS.contains(&extension))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	PathBuf::from(path)
+}
+
+fn precompress(args: &Arguments, path: &Path, contents: &[u8]) -> io::Result<()> {
+	let precompress = match args.precompress {
+		Some(precompress) if is_compressible(path) => precompress,
+		_ => return Ok(()),
+	};
+	let level = args.precompress_level.unwrap_or(DEFAULT_PRECOMPRESS_LEVEL);
+
+	if precompress.gzip {
+		let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+		encoder.write_all(contents)?;
+		write_one(args, &with_suffix(path, ".gz"), &encoder.finish()?)?;
+	}
+
+	if precompress.brotli {
+		let mut compressed = Vec::new();
+		{
+			let mut encoder = CompressorWriter::new(&mut compressed, 4096, level, 22);
+			encoder.write_all(contents)?;
+		}
+		write_one(args, &with_suffix(path, ".br"), &compressed)?;
+	}
+
+	Ok(())
+}
+
+pub fn create_dir_all(args: &Arguments, path: &Path) -> io::Result<()> {
+	if is_dry_run(args) {
+		return Ok(());
+	}
+
+	std::fs::create_dir_all(path)
+}
+
+pub fn remove_dir_all(args: &Arguments, path: &Path) -> io::Result<()> {
+	if is_dry_run(args) {
+		if path.exists() {
+			info!(args, "Would delete '{}'", path.to_string_lossy());
+		}
+		return Ok(());
+	}
+
+	verbose!(args, "Deleting '{}'", path.to_string_lossy());
+	std::fs::remove_dir_all(path)
+}
@@ -0,0 +1,164 @@
+use crate::minify::{tag_name, BLOCK_ELEMENTS, RAW_ELEMENTS};
+use crate::sanitize::find_tag_end;
+
+//Block elements which never have contents and so never open a deeper level
+const VOID_ELEMENTS: &[&str] = &["!doctype", "meta", "link", "hr", "br"];
+
+fn find_raw_end(html: &str, name: &str) -> usize {
+	let closing = format!("</{}", name);
+	let raw_end = html
+		.as_bytes()
+		.windows(closing.len())
+		.position(|window| window.eq_ignore_ascii_case(closing.as_bytes()))
+		.unwrap_or(html.len());
+
+	match html[raw_end..].find('>') {
+		Some(tag_end) => raw_end + tag_end + 1,
+		None => html.len(),
+	}
+}
+
+struct Printer {
+	output: String,
+	depth: usize,
+	line_ended: bool,
+	pending_space: bool,
+}
+
+impl Printer {
+	fn start_line(&mut self) {
+		self.output
+			.truncate(self.output.trim_end_matches(' ').len());
+		if !self.output.is_empty() && !self.output.ends_with('\n') {
+			self.output.push('\n');
+		}
+		for _ in 0..self.depth {
+			self.output.push('\t');
+		}
+
+		self.line_ended = false;
+		self.pending_space = false;
+	}
+
+	fn push_block(&mut self, text: &str) {
+		self.start_line();
+		self.output.push_str(text);
+		self.line_ended = true;
+	}
+
+	fn push_inline(&mut self, text: &str) {
+		if self.line_ended {
+			self.start_line();
+		} else if self.pending_space {
+			self.output.push(' ');
+			self.pending_space = false;
+		}
+		self.output.push_str(text);
+	}
+
+	fn push_text(&mut self, text: &str) {
+		//Only ASCII whitespace collapses in HTML, a no-break space must stay as written
+		if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+			self.pending_space = true;
+		}
+
+		let mut words = text.split_ascii_whitespace().peekable();
+
+		while let Some(word) = words.next() {
+			self.push_inline(word);
+			self.pending_space = words.peek().is_some();
+		}
+
+		if text.ends_with(|c: char| c.is_ascii_whitespace()) {
+			self.pending_space = true;
+		}
+	}
+}
+
+/*
+ * NOTE: Only whitespace which can never be visible is touched. Each block
+ * element goes on its own line indented by how deeply it is nested while
+ * inline elements and text keep flowing along the line they are on, with
+ * runs of whitespace between them collapsed to a single space. Raw elements
+ * such as `<pre>` are copied through byte for byte.
+ */
+pub fn pretty_html(html: &str) -> String {
+	let mut printer = Printer {
+		output: String::with_capacity(html.len() * 2),
+		depth: 0,
+		line_ended: false,
+		pending_space: false,
+	};
+
+	let mut index = 0;
+	while index < html.len() {
+		let rest = &html[index..];
+
+		if rest.starts_with("<!--") {
+			let end = match rest.find("-->") {
+				Some(end) => end + "-->".len(),
+				None => rest.len(),
+			};
+
+			//Between blocks a comment gets a line of its own, within text it stays put
+			if printer.line_ended {
+				printer.push_block(&rest[..end]);
+			} else {
+				printer.push_inline(&rest[..end]);
+			}
+			index += end;
+			continue;
+		}
+
+		if rest.starts_with('<') {
+			let end = find_tag_end(rest)
+				.or_else(|| rest.find('>').map(|end| end + 1))
+				.unwrap_or(rest.len());
+
+			let tag = &rest[..end];
+			let name = tag_name(tag);
+			let is_closing = tag.starts_with("</");
+			let is_block = BLOCK_ELEMENTS.contains(&name.as_str());
+
+			if !is_closing && RAW_ELEMENTS.contains(&name.as_str()) {
+				let raw_end = end + find_raw_end(&rest[end..], &name);
+				if is_block {
+					printer.push_block(&rest[..raw_end]);
+				} else {
+					printer.push_inline(&rest[..raw_end]);
+				}
+
+				index += raw_end;
+				continue;
+			}
+
+			if !is_block {
+				printer.push_inline(tag);
+			} else if is_closing {
+				printer.depth = printer.depth.saturating_sub(1);
+				printer.push_block(tag);
+			} else {
+				printer.push_block(tag);
+				if !VOID_ELEMENTS.contains(&name.as_str()) && !tag.ends_with("/>") {
+					printer.depth += 1;
+				}
+			}
+
+			index += end;
+			continue;
+		}
+
+		let end = rest.find('<').unwrap_or(rest.len());
+		printer.push_text(&rest[..end]);
+		index += end;
+	}
+
+	let trimmed = printer
+		.output
+		.trim_end_matches(|c: char| c.is_ascii_whitespace());
+	printer.output.truncate(trimmed.len());
+	if !printer.output.is_empty() {
+		printer.output.push('\n');
+	}
+	printer.output
+}
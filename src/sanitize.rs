@@ -0,0 +1,386 @@
+//Anything not listed here is dropped, though the text inside of it is kept
+const ALLOWED_ELEMENTS: &[&str] = &[
+	"a",
+	"abbr",
+	"b",
+	"blockquote",
+	"br",
+	"caption",
+	"cite",
+	"code",
+	"col",
+	"colgroup",
+	"dd",
+	"del",
+	"details",
+	"div",
+	"dl",
+	"dt",
+	"em",
+	"figcaption",
+	"figure",
+	"h1",
+	"h2",
+	"h3",
+	"h4",
+	"h5",
+	"h6",
+	"hr",
+	"i",
+	"img",
+	"ins",
+	"kbd",
+	"li",
+	"mark",
+	"ol",
+	"p",
+	"pre",
+	"q",
+	"s",
+	"samp",
+	"small",
+	"span",
+	"strong",
+	"sub",
+	"summary",
+	"sup",
+	"table",
+	"tbody",
+	"td",
+	"tfoot",
+	"th",
+	"thead",
+	"time",
+	"tr",
+	"u",
+	"ul",
+];
+
+//Dropped along with everything inside of them, their contents are never meant to be read
+const DROPPED_ELEMENTS: &[&str] = &[
+	"script", "style", "iframe", "object", "embed", "template", "noscript", "textarea",
+];
+
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+	"alt", "cite", "class", "colspan", "datetime", "dir", "height", "href", "id", "lang", "open",
+	"rowspan", "src", "start", "title", "width",
+];
+
+const URL_ATTRIBUTES: &[&str] = &["cite", "href", "src"];
+
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/*
+ * NOTE: Raw HTML reaches us one event at a time and an element opened in
+ * one event may only be closed several events later, so the sanitizer is
+ * kept for a whole post to remember that it is inside a dropped element.
+ */
+#[derive(Default)]
+pub struct Sanitizer {
+	dropping: Option<String>,
+}
+
+impl Sanitizer {
+	pub fn clean(&mut self, html: &str) -> String {
+		let mut output = String::with_capacity(html.len());
+		let mut rest = html;
+
+		while !rest.is_empty() {
+			let tag_start = rest.find('<').unwrap_or(rest.len());
+			if self.dropping.is_none() {
+				output.push_str(&rest[..tag_start]);
+			}
+			rest = &rest[tag_start..];
+			if rest.is_empty() {
+				break;
+			}
+
+			//Comments carry metadata and markers such as `<!--more-->`, anything else is dropped
+			if rest.starts_with("<!--") {
+				let end = comment_end(rest);
+				let comment = &rest[..end];
+				if self.dropping.is_none() && is_kept_comment(comment) {
+					output.push_str(comment);
+				}
+				rest = &rest[end..];
+				continue;
+			}
+
+			//Only a letter, `/` or `!` can begin a tag, anything else is a stray `<` in text
+			let starts_tag = rest[1..]
+				.chars()
+				.next()
+				.is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+			let tag_end = match find_tag_end(rest).filter(|_| starts_tag) {
+				Some(tag_end) => tag_end,
+
+				//Never closed so it cannot be a tag, show it as the text it looks like
+				None => {
+					if self.dropping.is_none() {
+						output.push_str("&lt;");
+					}
+					rest = &rest[1..];
+					continue;
+				}
+			};
+
+			let tag = &rest[..tag_end];
+			rest = &rest[tag_end..];
+			self.push_tag(&mut output, tag);
+		}
+
+		output
+	}
+
+	fn push_tag(&mut self, output: &mut String, tag: &str) {
+		let closing = tag.starts_with("</");
+		let name = tag_name(tag);
+
+		if let Some(dropping) = &self.dropping {
+			if closing && *dropping == name {
+				self.dropping = None;
+			}
+			return;
+		}
+
+		if DROPPED_ELEMENTS.contains(&name.as_str()) {
+			let self_closing = tag.ends_with("/>");
+			if !closing && !self_closing {
+				self.dropping = Some(name);
+			}
+			return;
+		}
+
+		if !ALLOWED_ELEMENTS.contains(&name.as_str()) {
+			return;
+		}
+
+		if closing {
+			output.push_str("</");
+			output.push_str(&name);
+			output.push('>');
+			return;
+		}
+
+		output.push('<');
+		output.push_str(&name);
+		for (attribute, value) in attributes(tag) {
+			if !ALLOWED_ATTRIBUTES.contains(&attribute.as_str()) {
+				continue;
+			}
+
+			match value {
+				Some(value) => {
+					if URL_ATTRIBUTES.contains(&attribute.as_str()) && !is_safe_url(value) {
+						continue;
+					}
+					output.push(' ');
+					output.push_str(&attribute);
+					output.push_str("=\"");
+					output.push_str(&value.replace('"', "&quot;"));
+					output.push('"');
+				}
+
+				None => {
+					output.push(' ');
+					output.push_str(&attribute);
+				}
+			}
+		}
+		if tag.ends_with("/>") {
+			output.push_str(" /");
+		}
+		output.push('>');
+	}
+}
+
+/*
+ * NOTE: Browsers end a comment at whichever of `-->` or `--!>` comes first
+ * and treat `<!-->` and `<!--->` as complete empty comments, so the end has
+ * to be found the same way or markup could hide past where we think it ends.
+ */
+fn comment_end(text: &str) -> usize {
+	let after_open = &text["<!--".len()..];
+	if after_open.starts_with('>') {
+		return "<!-->".len();
+	}
+	if after_open.starts_with("->") {
+		return "<!--->".len();
+	}
+
+	let end = [
+		("-->", after_open.find("-->")),
+		("--!>", after_open.find("--!>")),
+	]
+	.into_iter()
+	.filter_map(|(closer, index)| Some(index? + closer.len()))
+	.min();
+	match end {
+		Some(end) => "<!--".len() + end,
+		None => text.len(),
+	}
+}
+
+//Only the markers and `<!--label: value-->` metadata the builder itself reads
+fn is_kept_comment(comment: &str) -> bool {
+	let contents = match comment
+		.strip_prefix("<!--")
+		.and_then(|comment| comment.strip_suffix("-->"))
+	{
+		Some(contents) => contents,
+		None => return false,
+	};
+
+	if comment == crate::TOC_MARKER || comment == crate::MORE_MARKER {
+		return true;
+	}
+
+	match contents.find(':') {
+		Some(colon_index) => {
+			let label = &contents[..colon_index];
+			!label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+		}
+		None => false,
+	}
+}
+
+fn tag_name(tag: &str) -> String {
+	tag.trim_start_matches('<')
+		.trim_start_matches('/')
+		.chars()
+		.take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+		.collect::<String>()
+		.to_lowercase()
+}
+
+//A `>` inside of a quoted attribute value does not end the tag
+pub fn find_tag_end(text: &str) -> Option<usize> {
+	let mut quote = None;
+	for (index, c) in text.char_indices().skip(1) {
+		match (quote, c) {
+			(None, '"' | '\'') => quote = Some(c),
+			(Some(open), c) if open == c => quote = None,
+			(None, '>') => return Some(index + 1),
+			(None, '<') => return None,
+			_ => {}
+		}
+	}
+	None
+}
+
+//Lowercased names each with their value, if given, still quoted as in the source
+fn attributes(tag: &str) -> Vec<(String, Option<&str>)> {
+	let inner = tag
+		.trim_start_matches('<')
+		.trim_end_matches('>')
+		.trim_end_matches('/');
+	let name_end = inner
+		.find(|c: char| c.is_whitespace())
+		.unwrap_or(inner.len());
+	let mut rest = &inner[name_end..];
+
+	let mut attributes = Vec::new();
+	loop {
+		rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+		if rest.is_empty() {
+			break;
+		}
+
+		let name_end = rest
+			.find(|c: char| c.is_whitespace() || c == '=')
+			.unwrap_or(rest.len());
+		let name = rest[..name_end].to_lowercase();
+		rest = rest[name_end..].trim_start();
+
+		let value = match rest.strip_prefix('=') {
+			Some(after) => {
+				let after = after.trim_start();
+				let (value, remaining) = match after.chars().next() {
+					Some(quote @ ('"' | '\'')) => {
+						let after = &after[1..];
+						let end = after.find(quote).unwrap_or(after.len());
+						(&after[..end], after.get(end + 1..).unwrap_or(""))
+					}
+
+					_ => {
+						let end = after
+							.find(|c: char| c.is_whitespace())
+							.unwrap_or(after.len());
+						(&after[..end], &after[end..])
+					}
+				};
+				rest = remaining;
+				Some(value)
+			}
+
+			None => None,
+		};
+
+		attributes.push((name, value));
+	}
+
+	attributes
+}
+
+//Browsers decode these before looking at the scheme, so must we
+fn decode_entities(text: &str) -> String {
+	let mut decoded = String::with_capacity(text.len());
+	let mut rest = text;
+
+	while let Some(index) = rest.find('&') {
+		decoded.push_str(&rest[..index]);
+		rest = &rest[index..];
+
+		let end = rest.find(';').filter(|end| *end <= 10);
+		let entity = end.map(|end| &rest[1..end]);
+		let character = entity.and_then(|entity| match entity.to_lowercase().as_str() {
+			"colon" => Some(':'),
+			"tab" => Some('\t'),
+			"newline" => Some('\n'),
+			"amp" => Some('&'),
+			entity => {
+				let number = entity.strip_prefix('#')?;
+				let code = match number.strip_prefix('x') {
+					Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+					None => number.parse().ok()?,
+				};
+				char::from_u32(code)
+			}
+		});
+
+		match (character, end) {
+			(Some(character), Some(end)) => {
+				decoded.push(character);
+				rest = &rest[end + 1..];
+			}
+
+			_ => {
+				decoded.push('&');
+				rest = &rest[1..];
+			}
+		}
+	}
+
+	decoded.push_str(rest);
+	decoded
+}
+
+//Relative links are always fine, anything with a scheme must be on the list
+pub fn is_safe_url(url: &str) -> bool {
+	let url = decode_entities(url)
+		.chars()
+		.filter(|c| !c.is_whitespace() && !c.is_control())
+		.collect::<String>()
+		.to_lowercase();
+
+	let scheme_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+	let prefix = &url[..scheme_end];
+	if prefix.contains('&') {
+		return false;
+	}
+
+	match prefix.split_once(':') {
+		Some((scheme, _)) => ALLOWED_SCHEMES.contains(&scheme),
+		None => true,
+	}
+}
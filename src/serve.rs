@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::arguments::Arguments;
+
+//Served from disk on every request so a rebuild from `--watch` shows up on refresh
+pub fn spawn(args: &Arguments, address: &str) -> JoinHandle<()> {
+	let server = match Server::http(address) {
+		Ok(server) => server,
+
+		Err(err) => {
+			eprintln!("Error serving on '{}': {}", address, err);
+			std::process::exit(-1);
+		}
+	};
+
+	info!(
+		args,
+		"Serving '{}' at http://{}",
+		args.output_dir.to_string_lossy(),
+		address
+	);
+
+	let args = args.clone();
+	std::thread::spawn(move || {
+		for request in server.incoming_requests() {
+			respond(&args, request);
+		}
+	})
+}
+
+fn respond(args: &Arguments, request: Request) {
+	let path = resolve(args, request.url());
+	let file = path
+		.as_ref()
+		.and_then(|path| Some((path, File::open(path).ok()?)));
+
+	let result = match file {
+		Some((path, file)) => {
+			let response = Response::from_file(file).with_header(content_type(path));
+			request.respond(response)
+		}
+
+		None => {
+			//The generated 404 page if there is one, otherwise a bare status
+			let not_found = args.output_dir.join("404.html");
+			match File::open(&not_found) {
+				Ok(file) => {
+					let response = Response::from_file(file)
+						.with_status_code(404)
+						.with_header(content_type(&not_found));
+					request.respond(response)
+				}
+
+				Err(_) => request.respond(Response::empty(404)),
+			}
+		}
+	};
+
+	if let Err(err) = result {
+		warning!(args, "Error responding to request: {}", err);
+	}
+}
+
+/*
+ * NOTE: Folders are answered with their `index.html` and extensionless
+ * paths fall back to a flat `.html` page. Any `..` is refused outright
+ * so that nothing outside of the output directory can be reached.
+ */
+fn resolve(args: &Arguments, url: &str) -> Option<PathBuf> {
+	let mut url = url.split(['?', '#']).next().unwrap_or(url);
+	if let Some(base_path) = &args.base_path {
+		let base_path = base_path.trim_end_matches('/');
+		url = url.strip_prefix(base_path).unwrap_or(url);
+	}
+
+	let mut path = args.output_dir.clone();
+	for part in url.split('/').filter(|part| !part.is_empty()) {
+		let part = percent_decode(part)?;
+		if part == "." || part == ".." || part.contains(['/', '\\']) {
+			return None;
+		}
+		path.push(part);
+	}
+
+	if path.is_dir() {
+		path.push("index.html");
+	} else if !path.exists() {
+		let mut flat_page = path.into_os_string();
+		flat_page.push(".html");
+		path = PathBuf::from(flat_page);
+	}
+
+	Some(path).filter(|path| path.is_file())
+}
+
+fn percent_decode(part: &str) -> Option<String> {
+	let mut bytes = Vec::with_capacity(part.len());
+	let mut input = part.bytes();
+	while let Some(byte) = input.next() {
+		if byte == b'%' {
+			let high = (input.next()? as char).to_digit(16)?;
+			let low = (input.next()? as char).to_digit(16)?;
+			bytes.push((high * 16 + low) as u8);
+		} else {
+			bytes.push(byte);
+		}
+	}
+
+	String::from_utf8(bytes).ok()
+}
+
+fn content_type(path: &Path) -> Header {
+	let extension = path.extension().and_then(|extension| extension.to_str());
+	let content_type = match extension.unwrap_or_default() {
+		"html" => "text/html; charset=utf-8",
+		"css" => "text/css; charset=utf-8",
+		"js" => "text/javascript; charset=utf-8",
+		"json" => "application/json",
+		"xml" | "rss" => "application/xml",
+		"atom" => "application/atom+xml",
+		"txt" => "text/plain; charset=utf-8",
+		"svg" => "image/svg+xml",
+		"png" => "image/png",
+		"jpg" | "jpeg" => "image/jpeg",
+		"gif" => "image/gif",
+		"webp" => "image/webp",
+		"ico" => "image/x-icon",
+		"woff2" => "font/woff2",
+		_ => "application/octet-stream",
+	};
+
+	Header::from_bytes("Content-Type", content_type).expect("Content type header is always valid")
+}
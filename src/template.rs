@@ -1,10 +1,64 @@
 use std::collections::HashMap;
 
-pub fn format_template(template: String, values: HashMap<&str, &str>) -> String {
+use crate::error::BuildError;
+
+//Numeric `&#39;` rather than `&apos;` keeps this valid for both HTML and the XML feeds
+pub fn escape_html(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
+
+/*
+ * NOTE: Values are HTML escaped unless either the template asks for them raw
+ * with `$KEY!$` or the caller registered them as `KEY!`, meaning the value is
+ * markup by nature (like the list of formatted entries) and must never be
+ * escaped no matter how the template refers to it.
+ */
+fn lookup(values: &HashMap<&str, &str>, key: &str) -> Option<String> {
+	let (key, raw) = match key.strip_suffix('!') {
+		Some(key) => (key, true),
+		None => (key, false),
+	};
+
+	if let Some(value) = values.get(format!("{}!", key).as_str()) {
+		return Some(value.to_string());
+	}
+
+	let value = values.get(key)?;
+	if raw {
+		Some(value.to_string())
+	} else {
+		Some(escape_html(value))
+	}
+}
+
+pub fn format_template(
+	template: String,
+	values: HashMap<&str, &str>,
+) -> Result<String, BuildError> {
 	let mut output = template;
 
 	let mut index = 0;
 	while index < output.len() {
+		if output.as_bytes()[index] == b'$' && output.as_bytes().get(index + 1) == Some(&b'$') {
+			//Escaped literal `$`, keep one and skip past it so it never opens a substitution
+			output.remove(index);
+			index += 1;
+			continue;
+		}
+
 		if output.as_bytes()[index] == b'$' {
 			//Start of a substitution
 			let start = index;
@@ -21,16 +75,26 @@ pub fn format_template(template: String, values: HashMap<&str, &str>) -> String
 			}
 
 			if let Some(end) = end {
-				let key = &output[start + 1..end];
-				let value = match values.get(key) {
-					Some(value) => value,
-					None => {
-						eprintln!("Error failed to template substitute for key '{}'", key);
-						std::process::exit(-1);
-					}
+				let contents = &output[start + 1..end];
+
+				let value = match contents.split_once(':') {
+					//`$KEY:fallback$` quietly falls back when the value is absent or empty
+					Some((key, fallback)) => match lookup(&values, key) {
+						Some(value) if !value.is_empty() => value,
+						_ => fallback.to_string(),
+					},
+
+					//A bare `$KEY$` must exist so typos get caught
+					None => match lookup(&values, contents) {
+						Some(value) => value,
+						None => {
+							let key = contents.trim_end_matches('!').to_string();
+							return Err(BuildError::MissingTemplateKey { key });
+						}
+					},
 				};
 
-				output.replace_range(start..=end, value);
+				output.replace_range(start..=end, &value);
 				index = start + value.len();
 				continue;
 			}
@@ -39,5 +103,5 @@ pub fn format_template(template: String, values: HashMap<&str, &str>) -> String
 		index += 1;
 	}
 
-	output
+	Ok(output)
 }
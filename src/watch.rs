@@ -0,0 +1,61 @@
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+use chrono::Local;
+use notify::{RecursiveMode, Watcher};
+
+use crate::arguments::Arguments;
+use crate::build;
+
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub fn watch(args: &Arguments) {
+	let (sender, receiver) = channel();
+
+	let mut watcher = match notify::recommended_watcher(sender) {
+		Ok(watcher) => watcher,
+
+		Err(err) => {
+			eprintln!("Error starting file watcher: {}", err);
+			std::process::exit(-1);
+		}
+	};
+
+	let mut dirs = args.input_dirs.iter().collect::<Vec<_>>();
+	if let Some(fragments_dir) = &args.fragments_dir {
+		dirs.push(fragments_dir);
+	}
+	if let Some(assets_dir) = &args.assets_dir {
+		dirs.push(assets_dir);
+	}
+
+	for dir in dirs {
+		if let Err(err) = watcher.watch(dir, RecursiveMode::Recursive) {
+			eprintln!("Error watching dir '{}': {}", dir.to_string_lossy(), err);
+			std::process::exit(-1);
+		}
+	}
+
+	info!(args, "Watching for changes, press Ctrl-C to stop");
+
+	while let Ok(event) = receiver.recv() {
+		if let Err(err) = event {
+			warning!(args, "Error receiving file watcher event: {}", err);
+			continue;
+		}
+
+		//Editors tend to touch files several times per save, swallow the burst
+		loop {
+			match receiver.recv_timeout(DEBOUNCE) {
+				Ok(_) => continue,
+				Err(RecvTimeoutError::Timeout) => break,
+				Err(RecvTimeoutError::Disconnected) => return,
+			}
+		}
+
+		match build(args) {
+			Ok(summary) => info!(args, "[{}] {}", Local::now().format("%H:%M:%S"), summary),
+			Err(err) => eprintln!("{}", err),
+		}
+	}
+}